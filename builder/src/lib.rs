@@ -1,159 +1,2898 @@
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
+use syn::ext::IdentExt;
 use syn::parse_macro_input;
+use syn::spanned::Spanned;
+
+/// The errors found in a derive input, so that all of them can be reported
+/// together rather than only the first.
+#[derive(Default)]
+struct Errors(Option<syn::Error>);
+
+impl Errors {
+    fn push(&mut self, err: syn::Error) {
+        match self.0 {
+            Some(ref mut errors) => errors.combine(err),
+            None => self.0 = Some(err),
+        }
+    }
+
+    /// The value of `result`, recording its error if it has one.
+    fn ok<T>(&mut self, result: syn::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.push(err);
+                None
+            }
+        }
+    }
+}
+
+/// How the generated setters and `build()` receive the builder.
+#[derive(Clone, Copy, PartialEq)]
+enum Pattern {
+    /// Setters take and return `&mut Self`; `build(&self)` clones each field.
+    Mutable,
+    /// Setters take and return `Self`; `build(self)` moves each field.
+    Owned,
+}
+
+struct ContainerOptions {
+    /// Replaces the default `<Struct>Builder` name.
+    name: Option<Ident>,
+    /// Visibility of the builder, its error type and methods, instead of the
+    /// struct's own.
+    vis: Option<syn::Visibility>,
+    pattern: Pattern,
+    typestate: bool,
+    default: bool,
+    /// Whether fields get a `try_` setter, as with `#[builder(try_setter)]`
+    /// on each field.
+    try_setter: bool,
+    /// From `#[builder(from_env)]`, which generates `from_env()` and
+    /// `from_str_map()` parsing the fields from strings.
+    from_env: Option<syn::Path>,
+    /// From `#[builder(cli)]`, which generates `parse_args()` parsing the
+    /// fields from command line options.
+    cli: Option<syn::Path>,
+    /// Groups of fields from `#[builder(one_of("...", ...))]`, of which
+    /// `build()` requires exactly one to be set.
+    one_of: Vec<Vec<syn::LitStr>>,
+    setter: SetterOptions,
+    build_fn: BuildFnOptions,
+    /// Traits from `#[builder(derive(...))]` to derive on the builder.
+    derives: Vec<syn::Path>,
+    /// Attributes from `#[builder(struct_attr(...))]` to put on the builder.
+    struct_attrs: Vec<syn::Meta>,
+}
+
+/// Options from `#[builder(build_fn(...))]` on the struct.
+#[derive(Default)]
+struct BuildFnOptions {
+    /// A function taking `&<Builder>` and returning `Result<(), String>`,
+    /// called by `build()` before constructing the struct.
+    validate: Option<syn::Path>,
+    /// Replaces the default `build` name of the method.
+    name: Option<Ident>,
+}
+
+fn parse_build_fn_options(
+    list: &syn::MetaList,
+    build_fn: &mut BuildFnOptions,
+    errors: &mut Errors,
+) {
+    for nested in list.nested.iter() {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit_str),
+                ..
+            })) if path.is_ident("validate") => {
+                build_fn.validate = errors.ok(lit_str.parse());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit_str),
+                ..
+            })) if path.is_ident("name") => {
+                build_fn.name = errors.ok(parse_ident(lit_str));
+            }
+            _ => errors.push(syn::Error::new_spanned(
+                nested,
+                "expected `build_fn(validate = \"...\")` or `build_fn(name = \"...\")`",
+            )),
+        }
+    }
+}
+
+/// Options from `#[builder(setter(...))]`, on either the struct or a field.
+#[derive(Default)]
+struct SetterOptions {
+    into: bool,
+    /// Prepended with an underscore to the field name to name its setter.
+    prefix: Option<String>,
+    /// The name of a field's setter; not allowed on the struct.
+    name: Option<Ident>,
+    /// Leaves a field out of the builder entirely, so `build()` always uses
+    /// its default; not allowed on the struct.
+    skip: Option<syn::Path>,
+    /// Gives a field's setter the closure's parameters, and stores what its
+    /// body returns; not allowed on the struct.
+    transform: Option<syn::ExprClosure>,
+    /// Makes the setter of a `Box<T>`, `Rc<T>` or `Arc<T>` field take a `T`;
+    /// not allowed on the struct.
+    wrap: Option<syn::Path>,
+}
+
+fn parse_setter_options(list: &syn::MetaList, setter: &mut SetterOptions, errors: &mut Errors) {
+    for nested in list.nested.iter() {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("into") => {
+                setter.into = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("skip") => {
+                setter.skip = Some(path.clone());
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("wrap") => {
+                setter.wrap = Some(path.clone());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit_str),
+                ..
+            })) if path.is_ident("prefix") => {
+                // The prefix must itself be an identifier to make one.
+                setter.prefix = errors
+                    .ok(parse_ident(lit_str))
+                    .map(|prefix| prefix.to_string());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit_str),
+                ..
+            })) if path.is_ident("name") => {
+                setter.name = errors.ok(parse_ident(lit_str));
+            }
+            _ => errors.push(syn::Error::new_spanned(
+                nested,
+                "expected one of `setter(into)`, `setter(skip)`, `setter(wrap)`, `setter(prefix = \"...\")`, `setter(name = \"...\")` or `setter(transform = |...| ...)`",
+            )),
+        }
+    }
+}
+
+/// The error for anything in `#[builder(...)]` on the struct which is not
+/// one of its options.
+const CONTAINER_OPTIONS: &str = "expected one of `name = \"...\"`, `vis = \"...\"`, `pattern = \"...\"`, `typestate`, `default`, `try_setter`, `from_env`, `cli`, `setter(...)`, `build_fn(...)`, `one_of(...)`, `derive(...)` or `struct_attr(...)`";
+
+/// The error for anything in `#[builder(...)]` on a field which is not one of
+/// its options.
+const FIELD_OPTIONS: &str = "expected one of `each = \"...\"`, `default`, `default = \"...\"`, `name = \"...\"`, `vis = \"...\"`, `optional`, `required`, `sub_builder`, `try_setter`, `merge = \"...\"`, `env = \"...\"`, `requires = \"...\"`, `conflicts_with = \"...\"` or `setter(...)`";
+
+fn parse_container_options(attrs: &[syn::Attribute], errors: &mut Errors) -> ContainerOptions {
+    let mut options = ContainerOptions {
+        name: None,
+        vis: None,
+        pattern: Pattern::Mutable,
+        typestate: false,
+        default: false,
+        try_setter: false,
+        from_env: None,
+        cli: None,
+        one_of: vec![],
+        setter: SetterOptions::default(),
+        build_fn: BuildFnOptions::default(),
+        derives: vec![],
+        struct_attrs: vec![],
+    };
+    for attr in attrs.iter().filter(|a| a.path.is_ident("builder")) {
+        let list = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list,
+            Ok(meta) => {
+                errors.push(syn::Error::new_spanned(meta, CONTAINER_OPTIONS));
+                continue;
+            }
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ref path,
+                    lit: syn::Lit::Str(ref lit_str),
+                    ..
+                })) if path.is_ident("pattern") => {
+                    options.pattern = match lit_str.value().as_str() {
+                        "mutable" => Pattern::Mutable,
+                        "owned" => Pattern::Owned,
+                        _ => {
+                            errors.push(syn::Error::new_spanned(
+                                lit_str,
+                                "expected \"mutable\" or \"owned\"",
+                            ));
+                            options.pattern
+                        }
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ref path,
+                    lit: syn::Lit::Str(ref lit_str),
+                    ..
+                })) if path.is_ident("name") => {
+                    options.name = errors.ok(parse_ident(lit_str));
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ref path,
+                    lit: syn::Lit::Str(ref lit_str),
+                    ..
+                })) if path.is_ident("vis") => {
+                    options.vis = errors.ok(lit_str.parse());
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("typestate") => {
+                    options.typestate = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("default") => {
+                    options.default = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("try_setter") => {
+                    options.try_setter = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("from_env") => {
+                    options.from_env = Some(path.clone());
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("cli") => {
+                    options.cli = Some(path.clone());
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("setter") =>
+                {
+                    parse_setter_options(list, &mut options.setter, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("build_fn") =>
+                {
+                    parse_build_fn_options(list, &mut options.build_fn, errors);
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("derive") =>
+                {
+                    for nested in list.nested.iter() {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) => {
+                                options.derives.push(path.clone());
+                            }
+                            _ => errors.push(syn::Error::new_spanned(
+                                nested,
+                                "expected the name of a trait to derive",
+                            )),
+                        }
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("one_of") =>
+                {
+                    let mut group = vec![];
+                    for nested in list.nested.iter() {
+                        match nested {
+                            syn::NestedMeta::Lit(syn::Lit::Str(ref lit_str)) => {
+                                group.push(lit_str.clone());
+                            }
+                            _ => errors.push(syn::Error::new_spanned(
+                                nested,
+                                "expected the name of a field, such as `\"token\"`",
+                            )),
+                        }
+                    }
+                    if group.len() < 2 {
+                        errors.push(syn::Error::new_spanned(
+                            list,
+                            "`one_of` needs the names of at least two fields",
+                        ));
+                    } else {
+                        options.one_of.push(group);
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::List(ref list))
+                    if list.path.is_ident("struct_attr") =>
+                {
+                    for nested in list.nested.iter() {
+                        match nested {
+                            syn::NestedMeta::Meta(ref meta) => {
+                                options.struct_attrs.push(meta.clone());
+                            }
+                            syn::NestedMeta::Lit(ref lit) => {
+                                errors.push(syn::Error::new_spanned(lit, "expected an attribute"))
+                            }
+                        }
+                    }
+                }
+                _ => errors.push(syn::Error::new_spanned(nested, CONTAINER_OPTIONS)),
+            }
+        }
+    }
+    if let Some(ref name) = options.setter.name {
+        errors.push(syn::Error::new_spanned(
+            name,
+            "`setter(name)` can only be given on a field",
+        ));
+    }
+    if let Some(ref skip) = options.setter.skip {
+        errors.push(syn::Error::new_spanned(
+            skip,
+            "`setter(skip)` can only be given on a field",
+        ));
+    }
+    if let Some(ref wrap) = options.setter.wrap {
+        errors.push(syn::Error::new_spanned(
+            wrap,
+            "`setter(wrap)` can only be given on a field",
+        ));
+    }
+    if options.typestate {
+        for path in options.from_env.iter().chain(options.cli.iter()) {
+            errors.push(syn::Error::new_spanned(
+                path,
+                format!(
+                    "`{}` cannot be used with `typestate`, whose builder type changes as fields are set",
+                    path.get_ident().unwrap(),
+                ),
+            ));
+        }
+        if let Some(group) = options.one_of.first() {
+            errors.push(syn::Error::new_spanned(
+                &group[0],
+                "`one_of` cannot be used with `typestate`, whose `build()` cannot fail",
+            ));
+        }
+        if let Some(ref validate) = options.build_fn.validate {
+            errors.push(syn::Error::new_spanned(
+                validate,
+                "`build_fn(validate)` cannot be used with `typestate`, whose `build()` cannot fail",
+            ));
+        }
+    }
+    options
+}
+
+/// What the builder stores for a field and which setters it gets.
+enum FieldKind {
+    /// An `Option<T>` field; the setter takes the inner `T` and `build()`
+    /// leaves it `None` if never called.
+    Optional(Box<syn::Type>),
+    /// A collection field with a `#[builder(each = "...")]` setter taking one
+    /// item at a time. The collection must implement `Default` and `Extend`.
+    Repeated {
+        each: Ident,
+        item: EachItem,
+        merge: Merge,
+    },
+    /// Any other field; it must be set before `build()`.
+    Required,
+    /// A field with `#[builder(setter(skip))]`, or a `PhantomData`, which has
+    /// no setter and is always given its default.
+    Skipped,
+    /// A field with `#[builder(sub_builder)]`, whose type also derives
+    /// `Builder`. The builder stores the field's own builder, hands it out
+    /// from the setter and builds it in `build()`. A nested builder with the
+    /// owned pattern needs this builder to use it too, as `build(&self)`
+    /// cannot move it out.
+    SubBuilder {
+        builder: Box<syn::Type>,
+        error: Box<syn::Path>,
+    },
+}
+
+/// How `merge()` combines a collection field of two builders, from
+/// `#[builder(merge = "...")]`.
+#[derive(Clone, Copy, PartialEq)]
+enum Merge {
+    /// The other builder's items are added to this one's.
+    Append,
+    /// The other builder's items, if it has any, replace this one's.
+    Replace,
+}
+
+/// What the `each` setter of a collection field takes.
+enum EachItem {
+    /// The element type, such as `T` in `Vec<T>` or `BTreeSet<T>`.
+    Element(Box<syn::Type>),
+    /// The key and value types of a map, taken as two arguments.
+    Entry(Box<syn::Type>, Box<syn::Type>),
+    /// Not apparent from the type; the setter accepts anything the
+    /// collection can be extended with.
+    Inferred,
+}
+
+impl EachItem {
+    fn from_collection(segment: &syn::PathSegment) -> EachItem {
+        let mut type_args = vec![];
+        if let syn::PathArguments::AngleBracketed(ref args) = segment.arguments {
+            for arg in args.args.iter() {
+                if let syn::GenericArgument::Type(ty) = arg {
+                    type_args.push(ty.clone());
+                }
+            }
+        }
+        let mut type_args = type_args.into_iter();
+        match (type_args.next(), type_args.next()) {
+            (Some(key), Some(value)) if segment.ident.to_string().ends_with("Map") => {
+                EachItem::Entry(Box::new(key), Box::new(value))
+            }
+            (Some(element), _) => EachItem::Element(Box::new(element)),
+            (None, _) => EachItem::Inferred,
+        }
+    }
+}
+
+struct BuilderField<'a> {
+    /// Names the builder's storage for the field; for tuple struct fields
+    /// this comes from `#[builder(name = "...")]`.
+    name: Ident,
+    /// The field's name with any `setter(prefix)`, or its `setter(name)`.
+    setter_name: Ident,
+    /// The field's name or index in the struct.
+    member: syn::Member,
+    ty: &'a syn::Type,
+    kind: FieldKind,
+    /// Expression giving the field's value when its setter was never called,
+    /// from `#[builder(default)]` on the field or the struct.
+    default: Option<proc_macro2::TokenStream>,
+    /// Whether the setters accept any `Into` the stored type, from
+    /// `#[builder(setter(into))]` on the field or the struct.
+    into: bool,
+    /// Whether the field also gets a `try_` setter taking anything `TryInto`
+    /// the stored type, from `#[builder(try_setter)]`.
+    try_setter: bool,
+    /// The environment variable from `#[builder(env = "...")]`, replacing the
+    /// prefixed field name read by `from_env()`.
+    env: Option<syn::LitStr>,
+    /// The field's doc comment, joined into one line.
+    doc: String,
+    /// Fields from `#[builder(requires = "...")]`, which `build()` requires
+    /// to be set when this one is.
+    requires: Vec<syn::LitStr>,
+    /// Fields from `#[builder(conflicts_with = "...")]`, which `build()`
+    /// requires not to be set when this one is.
+    conflicts_with: Vec<syn::LitStr>,
+    /// The closure from `#[builder(setter(transform = ...))]`.
+    transform: Option<syn::ExprClosure>,
+    /// With `#[builder(setter(wrap))]`, the function wrapping the value the
+    /// setter takes, such as `Box::new`, and the type of that value.
+    wrap: Option<(proc_macro2::TokenStream, syn::Type)>,
+    /// Visibility of the field's setters, if not that of the builder.
+    vis: Option<syn::Visibility>,
+}
+
+impl BuilderField<'_> {
+    /// Whether `build()` must fail (or, for a typestate builder, not exist)
+    /// while this field's setter has not been called.
+    fn is_required(&self) -> bool {
+        matches!(self.kind, FieldKind::Required) && self.default.is_none()
+    }
+
+    /// For a collection whose item type is not apparent, and so which cannot be
+    /// iterated, the builder's flag recording whether it has been given any
+    /// items.
+    fn set_flag(&self) -> Option<Ident> {
+        match self.kind {
+            FieldKind::Repeated {
+                item: EachItem::Inferred,
+                ..
+            } => Some(format_ident!("__{}_set", self.name)),
+            _ => None,
+        }
+    }
+
+    /// The tokens of the input naming the field, for errors about it.
+    fn tokens(&self) -> &dyn quote::ToTokens {
+        match self.member {
+            syn::Member::Named(ref ident) => ident,
+            syn::Member::Unnamed(_) => self.ty,
+        }
+    }
+}
+
+/// The builder and builder error types of a `#[builder(sub_builder)]` field,
+/// named after its type as `derive(Builder)` names them: `TlsConfigBuilder`
+/// and `TlsConfigBuilderError` for a `TlsConfig`.
+fn sub_builder_types(ty: &syn::Type) -> Option<(syn::Type, syn::Path)> {
+    match ty {
+        syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        }) => {
+            let mut builder = path.clone();
+            let last = builder.segments.last_mut().unwrap();
+            last.ident = format_ident!("{}Builder", last.ident);
+            let mut error = builder.clone();
+            let last = error.segments.last_mut().unwrap();
+            last.ident = format_ident!("{}Error", last.ident);
+            last.arguments = syn::PathArguments::None;
+            Some((
+                syn::Type::Path(syn::TypePath {
+                    qself: None,
+                    path: builder,
+                }),
+                error,
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// The function creating a `Box<T>`, `Rc<T>` or `Arc<T>`, and the `T`.
+fn wrapped_type(ty: &syn::Type) -> Option<(proc_macro2::TokenStream, syn::Type)> {
+    let path = match ty {
+        syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        }) => path,
+        _ => return None,
+    };
+    let new = if segments_match_tail(&path.segments, &["std", "boxed", "Box"])
+        || segments_match_tail(&path.segments, &["alloc", "boxed", "Box"])
+    {
+        quote!(std::boxed::Box::new)
+    } else if segments_match_tail(&path.segments, &["std", "rc", "Rc"])
+        || segments_match_tail(&path.segments, &["alloc", "rc", "Rc"])
+    {
+        quote!(std::rc::Rc::new)
+    } else if segments_match_tail(&path.segments, &["std", "sync", "Arc"])
+        || segments_match_tail(&path.segments, &["alloc", "sync", "Arc"])
+    {
+        quote!(std::sync::Arc::new)
+    } else {
+        return None;
+    };
+    match path.segments.last().unwrap().arguments {
+        syn::PathArguments::AngleBracketed(ref args) => match args.args.first() {
+            Some(syn::GenericArgument::Type(ty)) => Some((new, ty.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The `T` of an `Option<T>` field, however the path to `Option` is spelt.
+fn option_arg(ty: &syn::Type) -> Option<syn::Type> {
+    match ty {
+        syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        }) if segments_match_tail(&path.segments, &["std", "option", "Option"])
+            || segments_match_tail(&path.segments, &["core", "option", "Option"]) =>
+        {
+            // Anything but `Option<T>` is left for the compiler to reject.
+            match path.segments.last().unwrap().arguments {
+                syn::PathArguments::AngleBracketed(ref args) => match args.args.first() {
+                    Some(syn::GenericArgument::Type(ty)) => Some(ty.clone()),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `parse_meta` only accepts literals as values, so the closure of a field's
+/// `setter(transform = |...| ...)` is taken out of its attribute here,
+/// leaving the rest of the attribute for `parse_meta`.
+fn take_transform(
+    attr: &syn::Attribute,
+) -> syn::Result<Option<(syn::Attribute, syn::ExprClosure)>> {
+    use syn::parse::{ParseStream, Parser};
+
+    let group = match attr.tokens.clone().into_iter().next() {
+        Some(proc_macro2::TokenTree::Group(group)) => group,
+        _ => return Ok(None),
+    };
+    // The tokens of one item of a comma separated list, up to the comma.
+    fn item(input: ParseStream) -> syn::Result<proc_macro2::TokenStream> {
+        let mut tokens = proc_macro2::TokenStream::new();
+        while !input.is_empty() && !input.peek(syn::Token![,]) {
+            tokens.extend(Some(input.parse::<proc_macro2::TokenTree>()?));
+        }
+        Ok(tokens)
+    }
+    let mut transform = None;
+    let parser = |input: ParseStream| {
+        let mut items = vec![];
+        while !input.is_empty() {
+            if input.peek(syn::Ident)
+                && input.peek2(syn::token::Paren)
+                && input.fork().parse::<Ident>()? == "setter"
+            {
+                let setter: Ident = input.parse()?;
+                let content;
+                let paren = syn::parenthesized!(content in input);
+                let mut setter_items = vec![];
+                while !content.is_empty() {
+                    if content.peek(syn::Ident)
+                        && content.peek2(syn::Token![=])
+                        && content.fork().parse::<Ident>()? == "transform"
+                    {
+                        content.parse::<Ident>()?;
+                        content.parse::<syn::Token![=]>()?;
+                        transform = Some(content.parse::<syn::ExprClosure>()?);
+                    } else {
+                        setter_items.push(item(&content)?);
+                    }
+                    if !content.is_empty() {
+                        content.parse::<syn::Token![,]>()?;
+                    }
+                }
+                let mut setter_group = proc_macro2::Group::new(
+                    proc_macro2::Delimiter::Parenthesis,
+                    quote!(#(#setter_items),*),
+                );
+                setter_group.set_span(paren.span);
+                items.push(quote!(#setter #setter_group));
+            } else {
+                items.push(item(input)?);
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(items)
+    };
+    let items = parser.parse2(group.stream())?;
+    Ok(transform.map(|transform| {
+        let mut rest = proc_macro2::Group::new(group.delimiter(), quote!(#(#items),*));
+        rest.set_span(group.span());
+        let mut attr = attr.clone();
+        attr.tokens = quote!(#rest);
+        (attr, transform)
+    }))
+}
+
+fn builder_field<'a>(
+    field: &'a syn::Field,
+    index: usize,
+    options: &ContainerOptions,
+    errors: &mut Errors,
+) -> BuilderField<'a> {
+    let mut name = field.ident.clone();
+    let mut each: Option<syn::Ident> = None;
+    let mut default = None;
+    let mut setter = SetterOptions::default();
+    let mut vis = None;
+    let mut optional = false;
+    let mut required = false;
+    let mut sub_builder = false;
+    let mut try_setter = None;
+    let mut merge = None;
+    let mut env = None;
+    let mut requires = vec![];
+    let mut conflicts_with = vec![];
+    for attr in field.attrs.iter().filter(|a| a.path.is_ident("builder")) {
+        let mut meta = attr.parse_meta();
+        if meta.is_err() {
+            if let Ok(Some((attr, transform))) = take_transform(attr) {
+                setter.transform = Some(transform);
+                meta = attr.parse_meta();
+            }
+        }
+        match meta {
+            Ok(syn::Meta::List(ref list)) => {
+                for nested in list.nested.iter() {
+                    match nested {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("each") => each = errors.ok(parse_ident(lit_str)),
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("default") => {
+                            default = errors
+                                .ok(lit_str.parse::<syn::Expr>())
+                                .map(|expr| quote!(#expr));
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("name") => {
+                            if field.ident.is_some() {
+                                errors.push(syn::Error::new_spanned(
+                                    nested,
+                                    "`name` is only used on tuple struct fields",
+                                ));
+                            } else {
+                                name = errors.ok(parse_ident(lit_str));
+                            }
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("vis") => {
+                            vis = errors.ok(lit_str.parse());
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("merge") => {
+                            merge = Some(lit_str.clone());
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("env") => {
+                            env = Some(lit_str.clone());
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("requires") => {
+                            requires.push(lit_str.clone());
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            ref path,
+                            lit: syn::Lit::Str(ref lit_str),
+                            ..
+                        })) if path.is_ident("conflicts_with") => {
+                            conflicts_with.push(lit_str.clone());
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::Path(ref path))
+                            if path.is_ident("default") =>
+                        {
+                            default = Some(quote!(std::default::Default::default()));
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::Path(ref path))
+                            if path.is_ident("optional") =>
+                        {
+                            optional = true;
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::Path(ref path))
+                            if path.is_ident("required") =>
+                        {
+                            required = true;
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::Path(ref path))
+                            if path.is_ident("sub_builder") =>
+                        {
+                            sub_builder = true;
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::Path(ref path))
+                            if path.is_ident("try_setter") =>
+                        {
+                            try_setter = Some(path.clone());
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::List(ref setter_list))
+                            if setter_list.path.is_ident("setter") =>
+                        {
+                            parse_setter_options(setter_list, &mut setter, errors);
+                        }
+                        // A lone unknown option is pointed out with the whole
+                        // attribute, as the workshop's test 08 expects.
+                        _ if list.nested.len() == 1 => errors.push(syn::Error::new_spanned(
+                            list,
+                            "expected `builder(each = \"...\")`",
+                        )),
+                        _ => errors.push(syn::Error::new_spanned(nested, FIELD_OPTIONS)),
+                    }
+                }
+            }
+            Ok(meta) => errors.push(syn::Error::new_spanned(
+                meta,
+                "expected `builder(each = \"...\")`",
+            )),
+            Err(err) => errors.push(err),
+        }
+    }
+    let member = match field.ident {
+        Some(ref ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(syn::Index::from(index)),
+    };
+    let name = name.unwrap_or_else(|| format_ident!("field{}", index));
+    if optional && required {
+        errors.push(syn::Error::new_spanned(
+            &field.ty,
+            "`optional` cannot be combined with `required`",
+        ));
+    }
+    if required && default.is_some() {
+        errors.push(syn::Error::new_spanned(
+            &field.ty,
+            "`required` cannot be combined with `default`",
+        ));
+    }
+    if each.is_some() {
+        if optional || required {
+            errors.push(syn::Error::new_spanned(
+                &field.ty,
+                "`each` cannot be combined with `optional` or `required`",
+            ));
+        }
+        if default.is_some() {
+            errors.push(syn::Error::new_spanned(
+                &field.ty,
+                "`default` cannot be combined with `each`",
+            ));
+        }
+    }
+    let merge_lit = merge;
+    let merge = match merge_lit {
+        Some(ref lit_str) if each.is_none() => {
+            errors.push(syn::Error::new_spanned(
+                lit_str,
+                "`merge` is only used with `each`",
+            ));
+            Merge::Append
+        }
+        Some(ref lit_str) => match lit_str.value().as_str() {
+            "append" => Merge::Append,
+            "replace" => Merge::Replace,
+            _ => {
+                errors.push(syn::Error::new_spanned(
+                    lit_str,
+                    "expected \"append\" or \"replace\"",
+                ));
+                Merge::Append
+            }
+        },
+        None => Merge::Append,
+    };
+    if sub_builder {
+        if each.is_some() || default.is_some() || optional || required {
+            errors.push(syn::Error::new_spanned(
+                &field.ty,
+                "`sub_builder` cannot be combined with `each`, `default`, `optional` or `required`",
+            ));
+        }
+        if options.typestate {
+            errors.push(syn::Error::new_spanned(
+                &field.ty,
+                "`sub_builder` cannot be used with `typestate`, whose `build()` cannot fail",
+            ));
+        }
+    }
+    let kind = match option_arg(&field.ty) {
+        _ if setter.skip.is_some() || is_phantom_data(&field.ty) => {
+            if each.is_some() {
+                errors.push(syn::Error::new_spanned(
+                    &field.ty,
+                    "`each` cannot be combined with `setter(skip)`",
+                ));
+            }
+            FieldKind::Skipped
+        }
+        _ if sub_builder && !options.typestate => match sub_builder_types(&field.ty) {
+            Some((builder, error)) => FieldKind::SubBuilder {
+                builder: Box::new(builder),
+                error: Box::new(error),
+            },
+            None => {
+                errors.push(syn::Error::new_spanned(
+                    &field.ty,
+                    "`sub_builder` needs a struct type which derives `Builder`",
+                ));
+                FieldKind::Required
+            }
+        },
+        // The setter takes the whole `Option`, and `build()` fails without it.
+        Some(_) if required => FieldKind::Required,
+        Some(option_arg) => {
+            if each.is_some() {
+                errors.push(syn::Error::new_spanned(
+                    &field.ty,
+                    "`each` cannot be used on an `Option` field",
+                ));
+            }
+            FieldKind::Optional(Box::new(option_arg))
+        }
+        None => match (each, &field.ty) {
+            (Some(each), syn::Type::Path(syn::TypePath { ref path, .. })) => {
+                let item = EachItem::from_collection(path.segments.last().unwrap());
+                // Without an item type the collection cannot be iterated to
+                // append its items to another's, only replace it.
+                let merge = match item {
+                    EachItem::Inferred => {
+                        if let Some(ref lit_str) = merge_lit {
+                            if lit_str.value() == "append" {
+                                errors.push(syn::Error::new_spanned(
+                                    lit_str,
+                                    "`merge = \"append\"` needs a collection with its item type as a parameter, such as `Vec<T>`",
+                                ));
+                            }
+                        }
+                        Merge::Replace
+                    }
+                    _ => merge,
+                };
+                FieldKind::Repeated { each, item, merge }
+            }
+            (Some(_), ty) => {
+                errors.push(syn::Error::new_spanned(
+                    ty,
+                    "`each` needs a collection type such as `Vec<T>` or `HashMap<K, V>`",
+                ));
+                FieldKind::Required
+            }
+            // References, tuples, arrays and the like can only be set whole.
+            (None, _) => {
+                // The macro cannot see through a type alias of `Option`, so
+                // an `optional` field is set whole and defaults when unset.
+                if optional && default.is_none() && !options.default {
+                    default = Some(quote!(std::default::Default::default()));
+                }
+                FieldKind::Required
+            }
+        },
+    };
+    // A command line flag which is not given is off.
+    if options.cli.is_some()
+        && matches!(kind, FieldKind::Required)
+        && !required
+        && default.is_none()
+        && !options.default
+        && is_bool(&field.ty)
+    {
+        default = Some(quote!(false));
+    }
+    let value_type = match kind {
+        FieldKind::Optional(ref option_arg) => Some(&**option_arg),
+        FieldKind::Required => Some(&field.ty),
+        _ => None,
+    };
+    if let Some(ref transform) = setter.transform {
+        if value_type.is_none() || setter.wrap.is_some() || setter.into || try_setter.is_some() {
+            errors.push(syn::Error::new_spanned(
+                transform,
+                "`setter(transform)` cannot be combined with `each`, `sub_builder`, `setter(skip)`, `setter(wrap)`, `setter(into)` or `try_setter`",
+            ));
+        }
+        for input in transform.inputs.iter() {
+            if !matches!(input, syn::Pat::Type(_)) {
+                errors.push(syn::Error::new_spanned(
+                    input,
+                    "expected a parameter with a type, such as `s: &str`",
+                ));
+            }
+        }
+    }
+    let mut wrap = None;
+    if let Some(ref wrap_path) = setter.wrap {
+        if setter.into || try_setter.is_some() {
+            errors.push(syn::Error::new_spanned(
+                wrap_path,
+                "`setter(wrap)` cannot be combined with `setter(into)` or `try_setter`",
+            ));
+        }
+        wrap = value_type.and_then(wrapped_type);
+        if wrap.is_none() {
+            errors.push(syn::Error::new_spanned(
+                &field.ty,
+                "`setter(wrap)` needs a `Box<T>`, `Rc<T>` or `Arc<T>` field, or an `Option` of one",
+            ));
+        }
+    }
+    if options.typestate {
+        if let Some(lit_str) = requires.iter().chain(conflicts_with.iter()).next() {
+            errors.push(syn::Error::new_spanned(
+                lit_str,
+                "`requires` and `conflicts_with` cannot be used with `typestate`, whose `build()` cannot fail",
+            ));
+        }
+    }
+    if let Some(ref env) = env {
+        if options.from_env.is_none() {
+            errors.push(syn::Error::new_spanned(
+                env,
+                "`env` is only used with `#[builder(from_env)]` on the struct",
+            ));
+        }
+    }
+    for path in options.from_env.iter().chain(options.cli.iter()) {
+        let option = path.get_ident().unwrap();
+        match kind {
+            FieldKind::SubBuilder { .. } => errors.push(syn::Error::new_spanned(
+                &field.ty,
+                format!("`{}` cannot parse a `sub_builder` field", option),
+            )),
+            FieldKind::Repeated {
+                item: EachItem::Inferred,
+                ..
+            } => errors.push(syn::Error::new_spanned(
+                &field.ty,
+                format!("`{}` cannot tell the item type of this collection", option),
+            )),
+            _ => {}
+        }
+    }
+    if let Some(ref try_setter) = try_setter {
+        if value_type.is_none() {
+            errors.push(syn::Error::new_spanned(
+                try_setter,
+                "`try_setter` cannot be combined with `each`, `sub_builder` or `setter(skip)`",
+            ));
+        }
+    }
+    let setter_name = match setter.name {
+        Some(setter_name) => setter_name,
+        None => match setter.prefix.as_ref().or(options.setter.prefix.as_ref()) {
+            Some(prefix) => format_ident!("{}_{}", prefix, name.unraw()),
+            None => name.clone(),
+        },
+    };
+    BuilderField {
+        name,
+        setter_name,
+        member,
+        ty: &field.ty,
+        kind,
+        default,
+        into: setter.into || options.setter.into,
+        // A setter taking the closure's parameters or the wrapped value has
+        // nothing for a `try_` setter to convert into.
+        try_setter: (try_setter.is_some() || options.try_setter)
+            && setter.transform.is_none()
+            && setter.wrap.is_none(),
+        env,
+        doc: doc_comment(&field.attrs),
+        requires,
+        conflicts_with,
+        transform: setter.transform,
+        wrap,
+        vis,
+    }
+}
 
 #[proc_macro_derive(Builder, attributes(builder))]
 pub fn derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as syn::DeriveInput);
 
-    let struct_name = &ast.ident;
-    let builder_name = Ident::new(&format!("{}Builder", struct_name), Span::call_site());
+    let type_name = &ast.ident;
+    let mut errors = Errors::default();
+    let options = parse_container_options(&ast.attrs, &mut errors);
+
+    let tokens = match &ast.data {
+        // Tuple struct fields are set through setters named by their
+        // #[builder(name = "...")] attributes, or `field0`, `field1`, ...
+        syn::Data::Struct(syn::DataStruct {
+            fields:
+                syn::Fields::Named(syn::FieldsNamed {
+                    named: ref fields, ..
+                })
+                | syn::Fields::Unnamed(syn::FieldsUnnamed {
+                    unnamed: ref fields,
+                    ..
+                }),
+            ..
+        }) => {
+            let builder_name = match options.name {
+                Some(ref name) => name.clone(),
+                None => Ident::new(&format!("{}Builder", type_name), Span::call_site()),
+            };
+            expand_builder(
+                &ast,
+                &options,
+                None,
+                builder_name,
+                format_ident!("builder"),
+                fields,
+                &mut errors,
+            )
+        }
+        // Each variant with named fields gets its own builder, created by
+        // `<Enum>::<variant>_builder()`, whose build() returns the enum.
+        syn::Data::Enum(syn::DataEnum { ref variants, .. }) => {
+            if options.default {
+                errors.push(syn::Error::new_spanned(
+                    type_name,
+                    "`builder(default)` is only supported on structs",
+                ));
+            }
+            if let Some(ref name) = options.name {
+                errors.push(syn::Error::new_spanned(
+                    name,
+                    "`builder(name)` is only supported on structs, as an enum has a builder per variant",
+                ));
+            }
+            let mut tokens = proc_macro2::TokenStream::new();
+            for variant in variants.iter() {
+                if let syn::Fields::Named(syn::FieldsNamed { ref named, .. }) = variant.fields {
+                    let variant_name = &variant.ident;
+                    let builder_name = Ident::new(
+                        &format!("{}{}Builder", type_name, variant_name),
+                        Span::call_site(),
+                    );
+                    let builder_fn = format_ident!(
+                        "{}_builder",
+                        to_snake_case(&variant_name.unraw().to_string())
+                    );
+                    tokens.extend(expand_builder(
+                        &ast,
+                        &options,
+                        Some(variant_name),
+                        builder_name,
+                        builder_fn,
+                        named,
+                        &mut errors,
+                    ));
+                }
+            }
+            tokens
+        }
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Unit,
+            ..
+        }) => {
+            errors.push(syn::Error::new_spanned(
+                type_name,
+                "`#[derive(Builder)]` is not supported on unit structs, which have no fields to set",
+            ));
+            proc_macro2::TokenStream::new()
+        }
+        syn::Data::Union(syn::DataUnion {
+            ref union_token, ..
+        }) => {
+            errors.push(syn::Error::new_spanned(
+                union_token,
+                "`#[derive(Builder)]` is only supported on structs and enums",
+            ));
+            proc_macro2::TokenStream::new()
+        }
+    };
+
+    match errors.0 {
+        Some(err) => err.to_compile_error().into(),
+        None => tokens.into(),
+    }
+}
+
+/// Generates one builder, for a struct or for `variant` of an enum.
+fn expand_builder(
+    ast: &syn::DeriveInput,
+    options: &ContainerOptions,
+    variant: Option<&Ident>,
+    builder_name: Ident,
+    builder_fn: Ident,
+    fields: &syn::punctuated::Punctuated<syn::Field, syn::Token![,]>,
+    errors: &mut Errors,
+) -> proc_macro2::TokenStream {
+    let type_name = &ast.ident;
+    let mut builder_fields = vec![];
+    for (index, field) in fields.iter().enumerate() {
+        builder_fields.push(builder_field(field, index, options, errors));
+    }
+
+    // With #[builder(default)] on the struct, build() creates one instance of
+    // the struct's own Default impl and moves unset fields out of it. A struct
+    // implementing Drop does not allow that, which the derive cannot detect.
+    let mut container_default = None;
+    if options.default {
+        let (_, ty_generics, _) = ast.generics.split_for_impl();
+        for field in builder_fields.iter_mut() {
+            if field.default.is_none()
+                && !matches!(
+                    field.kind,
+                    FieldKind::Repeated { .. } | FieldKind::SubBuilder { .. }
+                )
+            {
+                let member = &field.member;
+                field.default = Some(quote!(__default.#member));
+                container_default = Some(quote! {
+                    let __default: #type_name #ty_generics = std::default::Default::default();
+                });
+            }
+        }
+    }
+    for field in builder_fields.iter_mut() {
+        if matches!(field.kind, FieldKind::Skipped) && field.default.is_none() {
+            field.default = Some(quote!(std::default::Default::default()));
+        }
+    }
+
+    check_method_names(&builder_fields, options, errors);
+    let constraints = constraint_checks(&builder_fields, options, &builder_name, errors);
+
+    let input = BuilderInput {
+        type_name,
+        constructor: match variant {
+            Some(variant) => quote!(#type_name::#variant),
+            None => quote!(#type_name),
+        },
+        is_struct: variant.is_none(),
+        builder_name,
+        builder_fn,
+        vis: options.vis.as_ref().unwrap_or(&ast.vis),
+        generics: &ast.generics,
+        options,
+        fields: builder_fields,
+        container_default,
+        constraints,
+    };
+    if options.typestate {
+        typestate_builder(&input)
+    } else {
+        runtime_builder(&input)
+    }
+}
+
+/// Reports any field whose builder methods would have the same name as
+/// another method of the builder, such as a field `dir_opt` beside the
+/// `dir_opt()` generated for an optional `dir`, or a field `merge` beside
+/// `merge()`.
+fn check_method_names(fields: &[BuilderField], options: &ContainerOptions, errors: &mut Errors) {
+    // Each method's name, the field it is generated for if not for the
+    // builder itself, and whether it is one of that field's setters, which
+    // `setter(name)` can rename.
+    let mut methods = vec![];
+    let build_fn_name = match options.build_fn.name {
+        Some(ref name) => name.clone(),
+        None => format_ident!("build"),
+    };
+    methods.push((build_fn_name, None, false));
+    if !options.typestate {
+        methods.push((format_ident!("missing_fields"), None, false));
+        methods.push((format_ident!("missing_field_paths"), None, false));
+        methods.push((format_ident!("merge"), None, false));
+    }
+    if options.from_env.is_some() {
+        methods.push((format_ident!("from_env"), None, false));
+        methods.push((format_ident!("from_str_map"), None, false));
+    }
+    if options.cli.is_some() {
+        methods.push((format_ident!("parse_args"), None, false));
+        methods.push((format_ident!("help"), None, false));
+    }
+    for field in fields.iter() {
+        let setter_name = &field.setter_name;
+        match field.kind {
+            FieldKind::Optional(_) => {
+                methods.push((setter_name.clone(), Some(field), true));
+                methods.push((format_ident!("{}_opt", setter_name), Some(field), false));
+                methods.push((format_ident!("clear_{}", field.name), Some(field), false));
+            }
+            FieldKind::Repeated { ref each, .. } => {
+                methods.push((each.clone(), Some(field), true));
+                if each != setter_name {
+                    methods.push((setter_name.clone(), Some(field), true));
+                }
+                methods.push((format_ident!("clear_{}", field.name), Some(field), false));
+            }
+            FieldKind::Required | FieldKind::SubBuilder { .. } => {
+                methods.push((setter_name.clone(), Some(field), true));
+            }
+            FieldKind::Skipped => continue,
+        }
+        if field.try_setter && matches!(field.kind, FieldKind::Optional(_) | FieldKind::Required) {
+            methods.push((format_ident!("try_{}", setter_name), Some(field), false));
+        }
+        if !options.typestate && !matches!(field.kind, FieldKind::SubBuilder { .. }) {
+            methods.push((format_ident!("is_{}_set", field.name), Some(field), false));
+        }
+    }
+    for (i, (name, field, own)) in methods.iter().enumerate() {
+        for (other_name, other_field, other_own) in methods[..i].iter() {
+            if name != other_name {
+                continue;
+            }
+            // The error goes to the field whose setter can be renamed.
+            let (field, other_field) = match (field, other_field) {
+                (Some(field), Some(other_field)) if *other_own && !own => {
+                    (other_field, Some(field))
+                }
+                (Some(field), other_field) => (field, other_field.as_ref()),
+                (None, Some(other_field)) => (other_field, None),
+                (None, None) => continue,
+            };
+            let other = match other_field {
+                Some(other_field) => format!("the field `{}`", other_field.name.unraw()),
+                None => String::from("the builder itself"),
+            };
+            errors.push(syn::Error::new_spanned(
+                field.tokens(),
+                format!(
+                    "the builder would have two methods named `{}`, for this field and for {}; rename a setter with `setter(name = \"...\")`",
+                    name.unraw(),
+                    other,
+                ),
+            ));
+        }
+    }
+}
+
+/// The checks of the `requires`, `conflicts_with` and `one_of` constraints
+/// between fields, which name the fields by their builder's `is_<field>_set()`
+/// methods.
+fn constraint_checks(
+    fields: &[BuilderField],
+    options: &ContainerOptions,
+    builder_name: &Ident,
+    errors: &mut Errors,
+) -> Vec<proc_macro2::TokenStream> {
+    for field in fields.iter() {
+        if let FieldKind::SubBuilder { .. } = field.kind {
+            if let Some(lit_str) = field
+                .requires
+                .iter()
+                .chain(field.conflicts_with.iter())
+                .next()
+            {
+                errors.push(syn::Error::new_spanned(
+                    lit_str,
+                    "`requires` and `conflicts_with` cannot be used on a `sub_builder` field, which is never set, only built",
+                ));
+            }
+        }
+    }
+    let error_name = format_ident!("{}Error", builder_name);
+    let mut is_set = |lit_str: &syn::LitStr| {
+        let name = lit_str.value();
+        match fields.iter().find(|field| field.name.unraw() == name) {
+            Some(field) if matches!(field.kind, FieldKind::SubBuilder { .. }) => {
+                errors.push(syn::Error::new_spanned(
+                    lit_str,
+                    "a `sub_builder` field is never set, only built",
+                ));
+                None
+            }
+            Some(field) if !matches!(field.kind, FieldKind::Skipped) => {
+                Some(format_ident!("is_{}_set", field.name))
+            }
+            Some(_) => {
+                errors.push(syn::Error::new_spanned(
+                    lit_str,
+                    "a field without a setter is never set",
+                ));
+                None
+            }
+            None => {
+                errors.push(syn::Error::new_spanned(
+                    lit_str,
+                    format!("no field named `{}`", name),
+                ));
+                None
+            }
+        }
+    };
+    let violation = |message: String| {
+        quote! {
+            return std::result::Result::Err(#error_name::Constraint(std::string::String::from(#message)));
+        }
+    };
+
+    let mut checks = vec![];
+    for field in fields.iter() {
+        if let FieldKind::SubBuilder { .. } = field.kind {
+            continue;
+        }
+        let field_is_set = format_ident!("is_{}_set", field.name);
+        let field_str = field.name.unraw().to_string();
+        for required in field.requires.iter() {
+            if let Some(required_is_set) = is_set(required) {
+                let violation =
+                    violation(format!("'{}' requires '{}'", field_str, required.value()));
+                checks.push(quote! {
+                    if self.#field_is_set() && !self.#required_is_set() {
+                        #violation
+                    }
+                });
+            }
+        }
+        for conflicting in field.conflicts_with.iter() {
+            if let Some(conflicting_is_set) = is_set(conflicting) {
+                let violation = violation(format!(
+                    "'{}' conflicts with '{}'",
+                    field_str,
+                    conflicting.value()
+                ));
+                checks.push(quote! {
+                    if self.#field_is_set() && self.#conflicting_is_set() {
+                        #violation
+                    }
+                });
+            }
+        }
+    }
+    for group in options.one_of.iter() {
+        let group_is_set: Vec<_> = group.iter().filter_map(&mut is_set).collect();
+        let names: Vec<_> = group
+            .iter()
+            .map(|lit_str| format!("'{}'", lit_str.value()))
+            .collect();
+        let violation = violation(format!("exactly one of {} must be set", names.join(", ")));
+        checks.push(quote! {
+            let set = [#(self.#group_is_set()),*];
+            if set.iter().filter(|set| **set).count() != 1 {
+                #violation
+            }
+        });
+    }
+    checks
+}
+
+/// Everything the code generators need to know about the derive input.
+struct BuilderInput<'a> {
+    /// The struct or enum deriving `Builder`, which `build()` returns.
+    type_name: &'a Ident,
+    /// The struct, or the enum variant, that `build()` constructs.
+    constructor: proc_macro2::TokenStream,
+    /// Whether `type_name` is a struct, whose values can be turned back into
+    /// builders, rather than an enum.
+    is_struct: bool,
+    builder_name: Ident,
+    /// The associated function of `type_name` that creates the builder.
+    builder_fn: Ident,
+    vis: &'a syn::Visibility,
+    generics: &'a syn::Generics,
+    options: &'a ContainerOptions,
+    fields: Vec<BuilderField<'a>>,
+    /// Statement binding `__default` to the struct's `Default` value, if any
+    /// field falls back to it.
+    container_default: Option<proc_macro2::TokenStream>,
+    /// Statements in `build()` returning an error if the fields set break a
+    /// `requires`, `conflicts_with` or `one_of` constraint.
+    constraints: Vec<proc_macro2::TokenStream>,
+}
+
+impl BuilderInput<'_> {
+    /// The derives and attributes passed through to the builder struct.
+    fn struct_attrs(&self) -> proc_macro2::TokenStream {
+        let derives = &self.options.derives;
+        let struct_attrs = &self.options.struct_attrs;
+        let mut tokens = quote!(#(#[#struct_attrs])*);
+        if !derives.is_empty() {
+            tokens.extend(quote!(#[derive(#(#derives),*)]));
+        }
+        tokens
+    }
+
+    /// The struct's generic parameters without their defaults, as written
+    /// after `impl`, and the matching arguments as written after a type name.
+    fn generic_params(&self) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+        let mut params = vec![];
+        let mut args = vec![];
+        for param in self.generics.params.iter() {
+            match param {
+                syn::GenericParam::Type(type_param) => {
+                    let mut type_param = type_param.clone();
+                    type_param.eq_token = None;
+                    type_param.default = None;
+                    let ident = &type_param.ident;
+                    args.push(quote!(#ident));
+                    params.push(quote!(#type_param));
+                }
+                syn::GenericParam::Const(const_param) => {
+                    let mut const_param = const_param.clone();
+                    const_param.eq_token = None;
+                    const_param.default = None;
+                    let ident = &const_param.ident;
+                    args.push(quote!(#ident));
+                    params.push(quote!(#const_param));
+                }
+                syn::GenericParam::Lifetime(lifetime_def) => {
+                    let lifetime = &lifetime_def.lifetime;
+                    args.push(quote!(#lifetime));
+                    params.push(quote!(#lifetime_def));
+                }
+            }
+        }
+        (params, args)
+    }
+
+    fn build_fn_name(&self) -> Ident {
+        match self.options.build_fn.name {
+            Some(ref name) => name.clone(),
+            None => format_ident!("build"),
+        }
+    }
+
+    /// A field for builders of generic structs, so that every parameter of
+    /// the struct is used by the builder even if no stored field mentions it.
+    fn phantom_field(&self) -> Option<proc_macro2::TokenStream> {
+        if self.generics.params.is_empty() {
+            return None;
+        }
+        let type_name = self.type_name;
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        Some(quote! {
+            __phantom: std::marker::PhantomData<fn() -> #type_name #ty_generics>
+        })
+    }
+}
+
+/// The default builder, which checks at run time that required fields have
+/// been set and reports any that are missing through a generated
+/// `<Builder>Error` enum.
+fn runtime_builder(input: &BuilderInput) -> proc_macro2::TokenStream {
+    let type_name = input.type_name;
+    let constructor = &input.constructor;
+    let builder_name = &input.builder_name;
+    let build_fn_name = input.build_fn_name();
+    let builder_vis = input.vis;
+    let builder_fn = &input.builder_fn;
+    let pattern = input.options.pattern;
+    let container_default = &input.container_default;
+    let error_name = format_ident!("{}Error", builder_name);
+    let (self_param, self_ret, build_self) = match pattern {
+        Pattern::Mutable => (quote!(&mut self), quote!(&mut Self), quote!(&self)),
+        Pattern::Owned => (quote!(mut self), quote!(Self), quote!(self)),
+    };
+
+    let mut field_name_tokens = vec![];
+    let mut declr_tokens = vec![];
+    let mut fn_tokens = vec![];
+    let mut build_tokens = vec![];
+    let mut init_tokens = vec![];
+    // Builder fields seeded from a `value` of the struct, and from `self`.
+    let mut from_tokens = vec![];
+    let mut to_builder_tokens = vec![];
+    let mut merge_tokens = vec![];
+    let mut missing_tokens = vec![];
+    let mut missing_path_tokens = vec![];
+    // A builder with sub-builders also collects the paths of missing fields,
+    // its own and those of its sub-builders, in field order.
+    let has_sub_builders = input
+        .fields
+        .iter()
+        .any(|field| matches!(field.kind, FieldKind::SubBuilder { .. }));
+    for field in input.fields.iter() {
+        let field_name = &field.name;
+        let setter_name = &field.setter_name;
+        let vis = field.vis.as_ref().unwrap_or(input.vis);
+        let member = &field.member;
+        let field_type = field.ty;
+        let field_value = match pattern {
+            Pattern::Mutable => quote!(self.#field_name.clone()),
+            Pattern::Owned => quote!(self.#field_name),
+        };
+        let is_set_name = format_ident!("is_{}_set", field_name);
+        let name_str = field_name.unraw().to_string();
+        let is_set = match field.kind {
+            FieldKind::Optional(_) | FieldKind::Required => quote!(self.#field_name.is_some()),
+            FieldKind::Repeated { .. } => match field.set_flag() {
+                Some(set_flag) => quote!(self.#set_flag),
+                None => quote! {
+                    std::iter::IntoIterator::into_iter(&self.#field_name).next().is_some()
+                },
+            },
+            // A sub-builder is neither set nor unset, only complete or not,
+            // which its own `missing_fields()` tells.
+            FieldKind::SubBuilder { .. } | FieldKind::Skipped => quote!(),
+        };
+        if !is_set.is_empty() {
+            fn_tokens.push(quote! {
+                #vis fn #is_set_name(&self) -> bool {
+                    #is_set
+                }
+            });
+        }
+        if field.is_required() {
+            missing_tokens.push(quote! {
+                if !self.#is_set_name() {
+                    missing.push(#name_str);
+                }
+            });
+            missing_path_tokens.push(quote! {
+                if !self.#is_set_name() {
+                    missing.push(std::string::String::from(#name_str));
+                }
+            });
+        }
+        if let FieldKind::SubBuilder { .. } = field.kind {
+            missing_tokens.push(quote! {
+                if !self.#field_name.missing_fields().is_empty() {
+                    missing.push(#name_str);
+                }
+            });
+            missing_path_tokens.push(quote! {
+                missing.extend(
+                    self.#field_name
+                        .missing_field_paths()
+                        .into_iter()
+                        .map(|field| std::format!("{}.{}", #name_str, field)),
+                );
+            });
+        }
+        match field.kind {
+            FieldKind::Optional(ref option_arg) => {
+                declr_tokens.push(quote! {
+                    #field_name: std::option::Option<#option_arg>,
+                });
+                init_tokens.push(quote! {
+                    #field_name: std::option::Option::None,
+                });
+                fn_tokens.extend(optional_setters(
+                    field,
+                    option_arg,
+                    vis,
+                    &self_param,
+                    &self_ret,
+                ));
+                from_tokens.push(quote!(#field_name: value.#member,));
+                to_builder_tokens.push(quote! {
+                    #field_name: std::clone::Clone::clone(&self.#member),
+                });
+                merge_tokens.push(quote! {
+                    if other.#field_name.is_some() {
+                        self.#field_name = other.#field_name;
+                    }
+                });
+                build_tokens.push(quote! {
+                    let #field_name = #field_value;
+                });
+                field_name_tokens.push(match field.default {
+                    Some(ref default) => quote! {
+                        #member: if #field_name.is_some() { #field_name } else { #default },
+                    },
+                    None => quote! {#member: #field_name,},
+                });
+            }
+            FieldKind::Repeated { merge, .. } => {
+                declr_tokens.push(quote! {
+                    #field_name: #field_type,
+                });
+                init_tokens.push(quote! {
+                    #field_name: std::default::Default::default(),
+                });
+                let set_flag = field.set_flag();
+                fn_tokens.extend(repeated_setters(
+                    field,
+                    set_flag.as_ref(),
+                    vis,
+                    &self_param,
+                    &self_ret,
+                ));
+                from_tokens.push(quote!(#field_name: value.#member,));
+                to_builder_tokens.push(quote! {
+                    #field_name: std::clone::Clone::clone(&self.#member),
+                });
+                if let Some(ref set_flag) = set_flag {
+                    declr_tokens.push(quote!(#set_flag: bool,));
+                    init_tokens.push(quote!(#set_flag: false,));
+                    from_tokens.push(quote!(#set_flag: true,));
+                    to_builder_tokens.push(quote!(#set_flag: true,));
+                }
+                merge_tokens.push(match (merge, set_flag) {
+                    (Merge::Replace, Some(set_flag)) => quote! {
+                        if other.#set_flag {
+                            self.#field_name = other.#field_name;
+                            self.#set_flag = true;
+                        }
+                    },
+                    (Merge::Append, _) => quote! {
+                        std::iter::Extend::extend(&mut self.#field_name, other.#field_name);
+                    },
+                    (Merge::Replace, None) => quote! {
+                        let mut items = std::iter::IntoIterator::into_iter(other.#field_name).peekable();
+                        if items.peek().is_some() {
+                            self.#field_name = std::default::Default::default();
+                            std::iter::Extend::extend(&mut self.#field_name, items);
+                        }
+                    },
+                });
+                build_tokens.push(quote! {
+                    let #field_name = #field_value;
+                });
+                field_name_tokens.push(quote! {#member: #field_name,});
+            }
+            FieldKind::Skipped => {
+                let default = field.default.as_ref().unwrap();
+                field_name_tokens.push(quote! {#member: #default,});
+            }
+            FieldKind::SubBuilder {
+                ref builder,
+                ref error,
+            } => {
+                declr_tokens.push(quote! {
+                    #field_name: #builder,
+                });
+                init_tokens.push(quote! {
+                    #field_name: std::default::Default::default(),
+                });
+                fn_tokens.push(quote! {
+                    #vis fn #setter_name(&mut self) -> &mut #builder {
+                        &mut self.#field_name
+                    }
+                });
+                from_tokens.push(quote! {
+                    #field_name: std::convert::From::from(value.#member),
+                });
+                let nested_to_builder =
+                    quote_spanned!(field_type.span()=> self.#member.to_builder());
+                to_builder_tokens.push(quote!(#field_name: #nested_to_builder,));
+                merge_tokens.push(quote! {
+                    self.#field_name.__merge_in_place(other.#field_name);
+                });
+                // The nested builder's errors are reported as this builder's,
+                // naming its fields by their path from this one. An owned
+                // nested builder can only be moved out of an owned builder;
+                // otherwise the error points at the field.
+                let nested_build = quote_spanned!(field_type.span()=> self.#field_name.build());
+                build_tokens.push(quote! {
+                    let #field_name = match #nested_build {
+                        std::result::Result::Ok(#field_name) => std::option::Option::Some(#field_name),
+                        std::result::Result::Err(#error::UninitializedFields(fields)) => {
+                            __nested_uninitialized_fields.extend(
+                                fields.into_iter().map(|field| std::format!("{}.{}", #name_str, field)),
+                            );
+                            std::option::Option::None
+                        }
+                        std::result::Result::Err(#error::NestedUninitializedFields(fields)) => {
+                            __nested_uninitialized_fields.extend(
+                                fields.into_iter().map(|field| std::format!("{}.{}", #name_str, field)),
+                            );
+                            std::option::Option::None
+                        }
+                        std::result::Result::Err(#error::Validation(msg)) => {
+                            return std::result::Result::Err(#error_name::Validation(std::format!("{}: {}", #name_str, msg)));
+                        }
+                        std::result::Result::Err(#error::Constraint(msg)) => {
+                            return std::result::Result::Err(#error_name::Constraint(std::format!("{}: {}", #name_str, msg)));
+                        }
+                    };
+                });
+                field_name_tokens.push(quote! {#member: #field_name.unwrap(),});
+            }
+            FieldKind::Required => {
+                declr_tokens.push(quote! {
+                    #field_name: std::option::Option<#field_type>,
+                });
+                init_tokens.push(quote! {
+                    #field_name: std::option::Option::None,
+                });
+                let (generics, params, value) = value_setter(field, field_type);
+                fn_tokens.push(quote! {
+                    #vis fn #setter_name #generics(#self_param, #params) -> #self_ret {
+                        self.#field_name = Some(#value);
+                        self
+                    }
+                });
+                fn_tokens.push(try_setter(field, field_type, vis, &self_param, &self_ret));
+                from_tokens.push(quote! {
+                    #field_name: std::option::Option::Some(value.#member),
+                });
+                merge_tokens.push(quote! {
+                    if other.#field_name.is_some() {
+                        self.#field_name = other.#field_name;
+                    }
+                });
+                to_builder_tokens.push(quote! {
+                    #field_name: std::option::Option::Some(std::clone::Clone::clone(&self.#member)),
+                });
+                match field.default {
+                    Some(ref default) => {
+                        build_tokens.push(quote! {
+                            let #field_name = #field_value;
+                        });
+                        field_name_tokens.push(quote! {
+                            #member: match #field_name {
+                                std::option::Option::Some(#field_name) => #field_name,
+                                std::option::Option::None => #default,
+                            },
+                        });
+                    }
+                    None => {
+                        let nested_missing = if has_sub_builders {
+                            quote!(__nested_uninitialized_fields.push(std::string::String::from(#name_str));)
+                        } else {
+                            quote!()
+                        };
+                        build_tokens.push(quote! {
+                            let #field_name = #field_value;
+                            if #field_name.is_none() {
+                                __uninitialized_fields.push(#name_str);
+                                #nested_missing
+                            }
+                        });
+                        field_name_tokens.push(quote! {#member: #field_name.unwrap(),});
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(phantom_field) = input.phantom_field() {
+        declr_tokens.push(quote!(#phantom_field,));
+        init_tokens.push(quote!(__phantom: std::marker::PhantomData,));
+        from_tokens.push(quote!(__phantom: std::marker::PhantomData,));
+        to_builder_tokens.push(quote!(__phantom: std::marker::PhantomData,));
+    }
 
-    let fields = match &ast.data {
-        syn::Data::Struct(syn::DataStruct {
-            fields: syn::Fields::Named(syn::FieldsNamed { ref named, .. }),
-            ..
-        }) => named,
-        _ => panic!("\"#[derive(Builder)]\" only implemented for structs with named fields"),
+    let generics = input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let validate = input.options.build_fn.validate.as_ref().map(|validate| {
+        quote! {
+            if let std::result::Result::Err(msg) = #validate(&self) {
+                return std::result::Result::Err(#error_name::Validation(msg));
+            }
+        }
+    });
+    // A value is turned back into a builder with every field set from it, so
+    // it can be changed and built again; skipped fields revert to their
+    // defaults. Only the mutable pattern, whose `build()` already clones the
+    // fields, can copy them out of a borrowed value.
+    let mut round_trip = quote!();
+    if input.is_struct {
+        round_trip.extend(quote! {
+            impl #impl_generics std::convert::From<#type_name #ty_generics> for #builder_name #ty_generics #where_clause {
+                fn from(value: #type_name #ty_generics) -> Self {
+                    #builder_name {
+                        #(#from_tokens)*
+                    }
+                }
+            }
+        });
+        if pattern == Pattern::Mutable {
+            round_trip.extend(quote! {
+                impl #impl_generics #type_name #ty_generics #where_clause {
+                    #builder_vis fn to_builder(&self) -> #builder_name #ty_generics {
+                        #builder_name {
+                            #(#to_builder_tokens)*
+                        }
+                    }
+                }
+            });
+        }
+    }
+    let constraints = &input.constraints;
+    let (nested_declaration, nested_check) = if has_sub_builders {
+        (
+            quote! {
+                let mut __nested_uninitialized_fields: std::vec::Vec<std::string::String> = std::vec::Vec::new();
+            },
+            // Only a sub-builder's missing fields make the paths outnumber
+            // this builder's own.
+            quote! {
+                if __nested_uninitialized_fields.len() > __uninitialized_fields.len() {
+                    return std::result::Result::Err(#error_name::NestedUninitializedFields(__nested_uninitialized_fields));
+                }
+            },
+        )
+    } else {
+        (quote!(), quote!())
+    };
+    let from_strings = match input.options.from_env {
+        Some(_) => from_strings(input),
+        None => quote!(),
+    };
+    let cli_parser = match input.options.cli {
+        Some(_) => cli_parser(input),
+        None => quote!(),
+    };
+    let struct_attrs = input.struct_attrs();
+    let tokens = quote! {
+        #struct_attrs
+        #builder_vis struct #builder_name #generics #where_clause {
+            #(#declr_tokens)*
+        }
+
+        impl #impl_generics std::default::Default for #builder_name #ty_generics #where_clause {
+            fn default() -> Self {
+                #builder_name {
+                    #(#init_tokens)*
+                }
+            }
+        }
+
+        impl #impl_generics #builder_name #ty_generics #where_clause {
+            #(#fn_tokens)*
+
+            /// The required fields which have not been set, so `build()` would
+            /// fail; a sub-builder's field is named if any of its own are missing.
+            #builder_vis fn missing_fields(&self) -> std::vec::Vec<&'static str> {
+                let mut missing = std::vec::Vec::new();
+                #(#missing_tokens)*
+                missing
+            }
+
+            /// Like `missing_fields()`, but naming the fields missing from a
+            /// sub-builder by their path, as in `tls.cert_path`, the way
+            /// `build()` reports them.
+            #builder_vis fn missing_field_paths(&self) -> std::vec::Vec<std::string::String> {
+                let mut missing = std::vec::Vec::new();
+                #(#missing_path_tokens)*
+                missing
+            }
+
+            /// Takes every field set in `other`, which overrides this builder's.
+            #builder_vis fn merge(#self_param, other: Self) -> #self_ret {
+                self.__merge_in_place(other);
+                self
+            }
+
+            // `merge()` for either pattern, used by builders holding this
+            // one as a sub-builder.
+            #[doc(hidden)]
+            #builder_vis fn __merge_in_place(&mut self, other: Self) {
+                #(#merge_tokens)*
+            }
+
+            #builder_vis fn #build_fn_name(#build_self) -> std::result::Result<#type_name #ty_generics, #error_name> {
+                #validate
+                #(#constraints)*
+                let mut __uninitialized_fields: std::vec::Vec<&'static str> = std::vec::Vec::new();
+                #nested_declaration
+                #(#build_tokens)*
+                #nested_check
+                if !__uninitialized_fields.is_empty() {
+                    return std::result::Result::Err(#error_name::UninitializedFields(__uninitialized_fields));
+                }
+                #container_default
+
+                std::result::Result::Ok(#constructor{#(#field_name_tokens)*})
+            }
+        }
+
+        impl #impl_generics #type_name #ty_generics #where_clause {
+            #builder_vis fn #builder_fn() -> #builder_name #ty_generics {
+                std::default::Default::default()
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #builder_vis enum #error_name {
+            /// Required fields which had not been set when `build()` was called.
+            UninitializedFields(std::vec::Vec<&'static str>),
+            /// Required fields which had not been set, some of them in a
+            /// sub-builder, each named by its path, as in `tls.cert_path`.
+            NestedUninitializedFields(std::vec::Vec<std::string::String>),
+            /// The builder's contents were rejected by a validation check.
+            Validation(std::string::String),
+            /// The fields set break a `requires`, `conflicts_with` or `one_of`
+            /// constraint.
+            Constraint(std::string::String),
+        }
+
+        impl std::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let fields: std::vec::Vec<&str> = match self {
+                    #error_name::UninitializedFields(fields) => fields.clone(),
+                    #error_name::NestedUninitializedFields(fields) => {
+                        fields.iter().map(std::string::String::as_str).collect()
+                    }
+                    #error_name::Validation(msg) => return f.write_str(msg),
+                    #error_name::Constraint(msg) => return f.write_str(msg),
+                };
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "'{}'", field)?;
+                }
+                if fields.len() == 1 {
+                    f.write_str(" field has not been set")
+                } else {
+                    f.write_str(" fields have not been set")
+                }
+            }
+        }
+
+        impl std::error::Error for #error_name {}
+
+        #round_trip
+        #from_strings
+        #cli_parser
+    };
+
+    tokens
+}
+
+/// With `#[builder(from_env)]`, `from_env()` and `from_str_map()` creating
+/// the builder with each field parsed from a string through `FromStr`, and
+/// the `<Builder>ParseError` they return when a string does not parse.
+fn from_strings(input: &BuilderInput) -> proc_macro2::TokenStream {
+    let builder_name = &input.builder_name;
+    let builder_vis = input.vis;
+    let parse_error_name = format_ident!("{}ParseError", builder_name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut map_tokens = vec![];
+    let mut env_tokens = vec![];
+    for field in input.fields.iter() {
+        let field_name = &field.name;
+        let name_str = field_name.unraw().to_string();
+        let error = |value: proc_macro2::TokenStream, message: proc_macro2::TokenStream| {
+            quote! {
+                #parse_error_name {
+                    field: #name_str,
+                    value: std::string::String::from(#value),
+                    message: #message,
+                }
+            }
+        };
+        let store = match store_from_str(field, true, &error) {
+            Some(store) => store,
+            None => continue,
+        };
+        map_tokens.push(quote! {
+            if let std::option::Option::Some(value) = map.get(#name_str) {
+                let value: &str = value;
+                #store
+            }
+        });
+        let var_name = match field.env {
+            Some(ref env) => quote!(std::string::String::from(#env)),
+            None => {
+                let suffix = name_str.to_uppercase();
+                quote!(std::format!("{}{}", prefix, #suffix))
+            }
+        };
+        env_tokens.push(quote! {
+            match std::env::var(#var_name) {
+                std::result::Result::Ok(value) => {
+                    let value: &str = &value;
+                    #store
+                }
+                std::result::Result::Err(std::env::VarError::NotPresent) => {}
+                std::result::Result::Err(err) => {
+                    return std::result::Result::Err(#parse_error_name {
+                        field: #name_str,
+                        value: std::string::String::new(),
+                        message: std::string::ToString::to_string(&err),
+                    });
+                }
+            }
+        });
+    }
+
+    quote! {
+        impl #impl_generics #builder_name #ty_generics #where_clause {
+            /// A builder with the fields found in `map`, keyed by field name.
+            #builder_vis fn from_str_map(
+                map: &std::collections::HashMap<std::string::String, std::string::String>,
+            ) -> std::result::Result<Self, #parse_error_name> {
+                let mut builder: Self = std::default::Default::default();
+                #(#map_tokens)*
+                std::result::Result::Ok(builder)
+            }
+
+            /// A builder with the fields found in environment variables named
+            /// by `prefix` and the field name in upper case, or as given by
+            /// `#[builder(env = "...")]`.
+            #builder_vis fn from_env(prefix: &str) -> std::result::Result<Self, #parse_error_name> {
+                let mut builder: Self = std::default::Default::default();
+                #(#env_tokens)*
+                std::result::Result::Ok(builder)
+            }
+        }
+
+        /// A field's value, given as a string, which could not be parsed.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #builder_vis struct #parse_error_name {
+            /// The name of the field.
+            pub field: &'static str,
+            /// The string that could not be parsed.
+            pub value: std::string::String,
+            /// Why it could not be parsed.
+            pub message: std::string::String,
+        }
+
+        impl std::fmt::Display for #parse_error_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "invalid value '{}' for '{}': {}", self.value, self.field, self.message)
+            }
+        }
+
+        impl std::error::Error for #parse_error_name {}
+    }
+}
+
+/// The `#[builder(typestate)]` builder. Each required field gets a type
+/// parameter on the builder which is `()` until its setter is called and
+/// `(T,)` afterwards, so `build()` only exists once every required field has
+/// been set and can return the struct directly.
+fn typestate_builder(input: &BuilderInput) -> proc_macro2::TokenStream {
+    let type_name = input.type_name;
+    let constructor = &input.constructor;
+    let builder_name = &input.builder_name;
+    let build_fn_name = input.build_fn_name();
+    let builder_vis = input.vis;
+    let builder_fn = &input.builder_fn;
+    let fields = &input.fields;
+    let container_default = &input.container_default;
+    let state_params: Vec<Option<Ident>> = fields
+        .iter()
+        .map(|field| {
+            if field.is_required() {
+                Some(format_ident!(
+                    "__{}",
+                    to_camel_case(&field.name.unraw().to_string())
+                ))
+            } else {
+                None
+            }
+        })
+        .collect();
+    let all_params: Vec<&Ident> = state_params.iter().flatten().collect();
+    let (user_params, user_args) = input.generic_params();
+    let where_clause = &input.generics.where_clause;
+    // Setters which leave the type unchanged are implemented for every state.
+    let any_state = quote! {
+        impl<#(#user_params,)* #(#all_params),*> #builder_name<#(#user_args,)* #(#all_params),*> #where_clause
     };
 
-    let mut field_name_tokens = vec![];
     let mut declr_tokens = vec![];
+    let mut init_tokens = vec![];
     let mut fn_tokens = vec![];
     let mut build_tokens = vec![];
-    for field in fields.iter() {
-        let field_name = field.ident.as_ref().unwrap();
-        let msg = format!("'{}' field has not been set", stringify!(#field_name));
-        field_name_tokens.push(quote! {#field_name,});
-        let mut each: Option<syn::Ident> = None;
-        for attr in field.attrs.iter().filter(|a| a.path.is_ident("builder")) {
-            match attr.parse_meta() {
-                Ok(syn::Meta::List(ref list)) => {
-                    //syn::MetaList { ref nested, .. })) => {
-                    assert_eq!(list.nested.len(), 1);
-                    match list.nested.first() {
-                        Some(syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
-                            ref path,
-                            ref lit,
-                            ..
-                        }))) => {
-                            if !path.is_ident("each") {
-                                return syn::Error::new_spanned(
-                                    list,
-                                    "expected `builder(each = \"...\")`",
-                                )
-                                .to_compile_error()
-                                .into();
-                            }
-                            if let syn::Lit::Str(ref lit_str) = lit {
-                                each = Some(syn::Ident::new(&lit_str.value(), lit_str.span()))
-                            } else {
-                                panic!("whatever")
+    let mut unset_states = vec![];
+    let mut set_states = vec![];
+    let mut from_tokens = vec![];
+    for (field, state_param) in fields.iter().zip(state_params.iter()) {
+        let field_name = &field.name;
+        let setter_name = &field.setter_name;
+        let vis = field.vis.as_ref().unwrap_or(input.vis);
+        let member = &field.member;
+        let field_type = field.ty;
+        match field.kind {
+            FieldKind::Optional(ref value_type) => {
+                declr_tokens.push(quote! {
+                    #field_name: std::option::Option<#value_type>,
+                });
+                init_tokens.push(quote! {
+                    #field_name: std::option::Option::None,
+                });
+                let methods =
+                    optional_setters(field, value_type, vis, &quote!(mut self), &quote!(Self));
+                from_tokens.push(quote!(#field_name: value.#member,));
+                fn_tokens.push(quote! {
+                    #any_state {
+                        #(#methods)*
+                    }
+                });
+                build_tokens.push(match field.default {
+                    Some(ref default) => quote! {
+                        #member: if self.#field_name.is_some() { self.#field_name } else { #default },
+                    },
+                    None => quote! {
+                        #member: self.#field_name,
+                    },
+                });
+            }
+            FieldKind::Required if !field.is_required() => {
+                let default = field.default.as_ref().unwrap();
+                declr_tokens.push(quote! {
+                    #field_name: std::option::Option<#field_type>,
+                });
+                init_tokens.push(quote! {
+                    #field_name: std::option::Option::None,
+                });
+                let (generics, params, value) = value_setter(field, field_type);
+                let try_setter = try_setter(field, field_type, vis, &quote!(self), &quote!(Self));
+                fn_tokens.push(quote! {
+                    #any_state {
+                        #vis fn #setter_name #generics(mut self, #params) -> Self {
+                            self.#field_name = std::option::Option::Some(#value);
+                            self
+                        }
+
+                        #try_setter
+                    }
+                });
+                from_tokens.push(quote! {
+                    #field_name: std::option::Option::Some(value.#member),
+                });
+                build_tokens.push(quote! {
+                    #member: match self.#field_name {
+                        std::option::Option::Some(#field_name) => #field_name,
+                        std::option::Option::None => #default,
+                    },
+                });
+            }
+            FieldKind::Repeated { .. } => {
+                declr_tokens.push(quote! {
+                    #field_name: #field_type,
+                });
+                init_tokens.push(quote! {
+                    #field_name: std::default::Default::default(),
+                });
+                let methods = repeated_setters(field, None, vis, &quote!(mut self), &quote!(Self));
+                from_tokens.push(quote!(#field_name: value.#member,));
+                fn_tokens.push(quote! {
+                    #any_state {
+                        #(#methods)*
+                    }
+                });
+                build_tokens.push(quote! {
+                    #member: self.#field_name,
+                });
+            }
+            // Rejected with an error by `builder_field`.
+            FieldKind::SubBuilder { .. } => unreachable!(),
+            FieldKind::Skipped => {
+                let default = field.default.as_ref().unwrap();
+                build_tokens.push(quote! {
+                    #member: #default,
+                });
+            }
+            FieldKind::Required => {
+                let state_param = state_param.as_ref().unwrap();
+                declr_tokens.push(quote! {
+                    #field_name: #state_param,
+                });
+                init_tokens.push(quote! {
+                    #field_name: (),
+                });
+                let other_params: Vec<&Ident> = all_params
+                    .iter()
+                    .copied()
+                    .filter(|param| *param != state_param)
+                    .collect();
+                let states = |state| {
+                    all_params.iter().map(move |param| {
+                        if *param == state_param {
+                            quote!(#state)
+                        } else {
+                            quote!(#param)
+                        }
+                    })
+                };
+                let before = states(quote!(()));
+                let after = states(quote!((#field_type,)));
+                let (generics, params, value) = value_setter(field, field_type);
+                let mut moved: Vec<_> = fields
+                    .iter()
+                    .filter(|other| !matches!(other.kind, FieldKind::Skipped))
+                    .map(|other| {
+                        let other_name = &other.name;
+                        if other_name == field_name {
+                            quote!(#field_name: (#value,))
+                        } else {
+                            quote!(#other_name: self.#other_name)
+                        }
+                    })
+                    .collect();
+                if input.phantom_field().is_some() {
+                    moved.push(quote!(__phantom: self.__phantom));
+                }
+                let after_type = quote!(#builder_name<#(#user_args,)* #(#after),*>);
+                let try_setter = try_setter(field, field_type, vis, &quote!(self), &after_type);
+                fn_tokens.push(quote! {
+                    impl<#(#user_params,)* #(#other_params),*> #builder_name<#(#user_args,)* #(#before),*> #where_clause {
+                        #vis fn #setter_name #generics(self, #params) -> #after_type {
+                            #builder_name {
+                                #(#moved),*
                             }
                         }
-                        _ => panic!("whatever"),
+
+                        #try_setter
+                    }
+                });
+                build_tokens.push(quote! {
+                    #member: self.#field_name.0,
+                });
+                from_tokens.push(quote!(#field_name: (value.#member,),));
+                unset_states.push(quote!(()));
+                set_states.push(quote!((#field_type,)));
+            }
+        }
+    }
+
+    if let Some(phantom_field) = input.phantom_field() {
+        declr_tokens.push(quote!(#phantom_field,));
+        init_tokens.push(quote!(__phantom: std::marker::PhantomData,));
+        from_tokens.push(quote!(__phantom: std::marker::PhantomData,));
+    }
+
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    // A value converts into a builder with every required field set, ready
+    // to be changed and built again.
+    let round_trip = if input.is_struct {
+        quote! {
+            impl #impl_generics std::convert::From<#type_name #ty_generics> for #builder_name<#(#user_args,)* #(#set_states),*> #where_clause {
+                fn from(value: #type_name #ty_generics) -> Self {
+                    #builder_name {
+                        #(#from_tokens)*
                     }
                 }
-                _ => panic! {"Expected 'each=<name>'"},
             }
         }
-        match field.ty {
-            syn::Type::Path(syn::TypePath { ref path, .. }) => {
-                let segment = path.segments.first().unwrap();
-                if segment.ident == "Option" {
-                    assert!(each.is_none());
-                    let option_arg = match segment.arguments {
-                        syn::PathArguments::AngleBracketed(ref args) => &args.args,
-                        _ => panic!("expected angle brackets"),
-                    };
-                    declr_tokens.push(quote! {
-                        #field_name: std::option::Option<#option_arg>,
-                    });
-                    fn_tokens.push(quote! {
-                        pub fn #field_name(&mut self, #field_name: #option_arg) -> &mut Self {
-                            self.#field_name = Some(#field_name);
-                            self
-                        }
-                    });
-                    build_tokens.push(quote! {
-                        let #field_name = match self.#field_name {
-                            Some(ref #field_name) => Some(#field_name.clone()),
-                            None => None,
-                        };
-                    });
-                } else if let Some(each) = each {
-                    assert_eq!(segment.ident, "Vec");
-                    let field_type = &field.ty;
-                    declr_tokens.push(quote! {
-                        #field_name: #field_type,
-                    });
-                    let vec_type = match segment.arguments {
-                        syn::PathArguments::AngleBracketed(ref args) => &args.args,
-                        _ => panic!("expected angle brackets"),
+    } else {
+        quote!()
+    };
+    let struct_attrs = input.struct_attrs();
+    let tokens = quote!(
+        #struct_attrs
+        #builder_vis struct #builder_name<#(#user_params,)* #(#all_params),*> #where_clause {
+            #(#declr_tokens)*
+        }
+
+        #(#fn_tokens)*
+
+        impl #impl_generics #builder_name<#(#user_args,)* #(#set_states),*> #where_clause {
+            #builder_vis fn #build_fn_name(self) -> #type_name #ty_generics {
+                #container_default
+                #constructor {
+                    #(#build_tokens)*
+                }
+            }
+        }
+
+        impl #impl_generics #type_name #ty_generics #where_clause {
+            #builder_vis fn #builder_fn() -> #builder_name<#(#user_args,)* #(#unset_states),*> {
+                #builder_name {
+                    #(#init_tokens)*
+                }
+            }
+        }
+
+        #round_trip
+    );
+
+    tokens
+}
+
+/// The setters of an `Option<T>` field: one taking a `T`, one named with an
+/// `_opt` suffix taking an `Option<T>`, and `clear_<field>` resetting it to
+/// `None`. The `_opt` setter is never generic, even with `setter(into)`, so
+/// that a bare `None` can be passed to it.
+fn optional_setters(
+    field: &BuilderField,
+    option_arg: &syn::Type,
+    vis: &syn::Visibility,
+    self_param: &proc_macro2::TokenStream,
+    self_ret: &proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
+    let field_name = &field.name;
+    let setter_name = &field.setter_name;
+    let opt_setter_name = format_ident!("{}_opt", setter_name);
+    let clear_name = format_ident!("clear_{}", field_name);
+    let (generics, params, value) = value_setter(field, option_arg);
+    let try_setter = try_setter(field, option_arg, vis, self_param, self_ret);
+    vec![
+        try_setter,
+        quote! {
+            #vis fn #setter_name #generics(#self_param, #params) -> #self_ret {
+                self.#field_name = std::option::Option::Some(#value);
+                self
+            }
+        },
+        quote! {
+            #vis fn #opt_setter_name(#self_param, #field_name: std::option::Option<#option_arg>) -> #self_ret {
+                self.#field_name = #field_name;
+                self
+            }
+        },
+        quote! {
+            #vis fn #clear_name(#self_param) -> #self_ret {
+                self.#field_name = std::option::Option::None;
+                self
+            }
+        },
+    ]
+}
+
+/// With `#[builder(try_setter)]`, the `try_` setter of a field, which
+/// converts its argument with `TryInto` and passes it to the field's setter,
+/// or returns the conversion's error.
+fn try_setter(
+    field: &BuilderField,
+    value_type: &syn::Type,
+    vis: &syn::Visibility,
+    self_param: &proc_macro2::TokenStream,
+    self_ret: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if !field.try_setter {
+        return quote!();
+    }
+    let field_name = &field.name;
+    let setter_name = &field.setter_name;
+    let try_setter_name = format_ident!("try_{}", setter_name);
+    quote! {
+        #vis fn #try_setter_name<__V: std::convert::TryInto<#value_type>>(
+            #self_param,
+            #field_name: __V,
+        ) -> std::result::Result<#self_ret, <__V as std::convert::TryInto<#value_type>>::Error> {
+            let #field_name: #value_type = std::convert::TryInto::try_into(#field_name)?;
+            std::result::Result::Ok(self.#setter_name(#field_name))
+        }
+    }
+}
+
+/// The one-at-a-time setter of a collection field, which extends the
+/// collection with one item, unless it has the same name, the setter for the
+/// field itself, which extends it with any number of items, and
+/// `clear_<field>` emptying it. The setters also keep any `set_flag` of the
+/// field up to date.
+fn repeated_setters(
+    field: &BuilderField,
+    set_flag: Option<&Ident>,
+    vis: &syn::Visibility,
+    self_param: &proc_macro2::TokenStream,
+    self_ret: &proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
+    let field_name = &field.name;
+    let field_type = field.ty;
+    let setter_name = &field.setter_name;
+    let (each, item) = match field.kind {
+        FieldKind::Repeated {
+            ref each, ref item, ..
+        } => (each, item),
+        _ => unreachable!(),
+    };
+    let (mark_set, mark_unset) = match set_flag {
+        Some(set_flag) => (
+            quote!(self.#set_flag = true;),
+            quote!(self.#set_flag = false;),
+        ),
+        None => (quote!(), quote!()),
+    };
+    let mut setters = vec![];
+    match item {
+        EachItem::Element(element_type) => {
+            let (generics, arg_type, value) = setter_arg(each, element_type, field.into, "__V");
+            setters.push(quote! {
+                #vis fn #each #generics(#self_param, #each: #arg_type) -> #self_ret {
+                    std::iter::Extend::extend(&mut self.#field_name, std::iter::once(#value));
+                    self
+                }
+            });
+        }
+        EachItem::Entry(key_type, value_type) => {
+            let key = format_ident!("key");
+            let value = format_ident!("value");
+            let (_, key_arg_type, key_value) = setter_arg(&key, key_type, field.into, "__K");
+            let (_, value_arg_type, value_value) =
+                setter_arg(&value, value_type, field.into, "__V");
+            let generics = if field.into {
+                quote!(<__K: std::convert::Into<#key_type>, __V: std::convert::Into<#value_type>>)
+            } else {
+                quote!()
+            };
+            setters.push(quote! {
+                #vis fn #each #generics(#self_param, #key: #key_arg_type, #value: #value_arg_type) -> #self_ret {
+                    std::iter::Extend::extend(
+                        &mut self.#field_name,
+                        std::iter::once((#key_value, #value_value)),
+                    );
+                    self
+                }
+            });
+        }
+        EachItem::Inferred => {
+            setters.push(quote! {
+                #vis fn #each<__V>(#self_param, #each: __V) -> #self_ret
+                where
+                    #field_type: std::iter::Extend<__V>,
+                {
+                    std::iter::Extend::extend(&mut self.#field_name, std::iter::once(#each));
+                    #mark_set
+                    self
+                }
+            });
+        }
+    }
+    let clear_name = format_ident!("clear_{}", field_name);
+    setters.push(quote! {
+        #vis fn #clear_name(#self_param) -> #self_ret {
+            self.#field_name = std::default::Default::default();
+            #mark_unset
+            self
+        }
+    });
+    if each != setter_name {
+        let bound = match item {
+            EachItem::Element(element_type) => {
+                quote!(__I: std::iter::IntoIterator<Item = #element_type>)
+            }
+            EachItem::Entry(key_type, value_type) => {
+                quote!(__I: std::iter::IntoIterator<Item = (#key_type, #value_type)>)
+            }
+            EachItem::Inferred => quote! {
+                __I: std::iter::IntoIterator,
+                #field_type: std::iter::Extend<__I::Item>
+            },
+        };
+        setters.push(quote! {
+            #vis fn #setter_name<__I>(#self_param, #field_name: __I) -> #self_ret
+            where
+                #bound
+            {
+                std::iter::Extend::extend(&mut self.#field_name, #field_name);
+                #mark_set
+                self
+            }
+        });
+    }
+    setters
+}
+
+/// With `#[builder(cli)]`, `parse_args()` creating the builder from command
+/// line options named after the fields, `help()` describing the options with
+/// the fields' doc comments, and the `<Builder>ArgsError` that `parse_args()`
+/// returns for `--help` or for arguments it cannot use.
+fn cli_parser(input: &BuilderInput) -> proc_macro2::TokenStream {
+    let builder_name = &input.builder_name;
+    let builder_vis = input.vis;
+    let args_error_name = format_ident!("{}ArgsError", builder_name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut help = String::from("Options:\n");
+    let mut usages = vec![];
+    let mut arms = vec![];
+    for field in input.fields.iter() {
+        let field_name = &field.name;
+        // A collection's option adds one item, so is named after `each`.
+        let option_name = match field.kind {
+            FieldKind::Repeated { ref each, .. } => each.unraw().to_string(),
+            _ => field_name.unraw().to_string(),
+        };
+        let option = format!("--{}", option_name.replace('_', "-"));
+        let value_type = match field.kind {
+            FieldKind::Optional(ref option_arg) => Some(&**option_arg),
+            FieldKind::Required => Some(field.ty),
+            _ => None,
+        };
+        let is_flag = value_type.is_some_and(is_bool);
+        let error = |value: proc_macro2::TokenStream, message: proc_macro2::TokenStream| {
+            quote! {
+                #args_error_name::InvalidValue {
+                    option: #option,
+                    value: std::string::String::from(#value),
+                    message: #message,
+                }
+            }
+        };
+        let store = match store_from_str(field, false, &error) {
+            Some(store) => store,
+            None => continue,
+        };
+        // A flag is set to true by its option alone, or to `--flag=false`.
+        let value = if is_flag {
+            quote!(inline.unwrap_or_else(|| std::string::String::from("true")))
+        } else {
+            quote! {
+                match inline.or_else(|| args.next()) {
+                    std::option::Option::Some(value) => value,
+                    std::option::Option::None => {
+                        return std::result::Result::Err(#args_error_name::MissingValue(#option));
+                    }
+                }
+            }
+        };
+        arms.push(quote! {
+            #option => {
+                let value = #value;
+                let value: &str = &value;
+                #store
+            }
+        });
+        let usage = match field.kind {
+            _ if is_flag => option,
+            FieldKind::Repeated { .. } => format!("{} <VALUE>...", option),
+            _ => format!("{} <VALUE>", option),
+        };
+        usages.push((usage, field.doc.clone()));
+    }
+    usages.push((String::from("--help"), String::from("Print this help")));
+    let width = usages
+        .iter()
+        .map(|(usage, _)| usage.len())
+        .max()
+        .unwrap_or(0);
+    for (usage, doc) in usages.iter() {
+        if doc.is_empty() {
+            help.push_str(&format!("  {}\n", usage));
+        } else {
+            help.push_str(&format!("  {:width$}  {}\n", usage, doc, width = width));
+        }
+    }
+
+    quote! {
+        impl #impl_generics #builder_name #ty_generics #where_clause {
+            /// A builder with the fields given by command line options such
+            /// as `--current-dir <VALUE>`, which may also be written
+            /// `--current-dir=<VALUE>`. The program name should not be
+            /// included in `args`.
+            #builder_vis fn parse_args<__I: std::iter::IntoIterator<Item = std::string::String>>(
+                args: __I,
+            ) -> std::result::Result<Self, #args_error_name> {
+                let mut builder: Self = std::default::Default::default();
+                let mut args = std::iter::IntoIterator::into_iter(args);
+                while let std::option::Option::Some(arg) = args.next() {
+                    let (option, inline) = match arg.find('=') {
+                        std::option::Option::Some(index) if arg.starts_with("--") => (
+                            std::string::String::from(&arg[..index]),
+                            std::option::Option::Some(std::string::String::from(&arg[index + 1..])),
+                        ),
+                        _ => (arg.clone(), std::option::Option::None),
                     };
-                    fn_tokens.push(quote! {
-                        pub fn #each(&mut self, #each: #vec_type) -> &mut Self {
-                            self.#field_name.push(#each);
-                            self
+                    match option.as_str() {
+                        "--help" => {
+                            return std::result::Result::Err(#args_error_name::Help(
+                                std::string::String::from(Self::help()),
+                            ));
                         }
-                    });
-                    if each.to_string() != field_name.to_string() {
-                        fn_tokens.push(quote! {
-                            pub fn #field_name(&mut self, #field_name: #field_type) -> &mut Self {
-                                self.#field_name.extend(#field_name);
-                                self
-                            }
-                        });
+                        #(#arms)*
+                        _ => return std::result::Result::Err(#args_error_name::UnknownArgument(arg)),
                     }
-                    build_tokens.push(quote! {
-                        let #field_name = self.#field_name.clone();
-                    });
-                } else {
-                    let field_type = &field.ty;
-                    declr_tokens.push(quote! {
-                        #field_name: std::option::Option<#field_type>,
-                    });
-                    fn_tokens.push(quote! {
-                        pub fn #field_name(&mut self, #field_name: #field_type) -> &mut Self {
-                            self.#field_name = Some(#field_name);
-                            self
-                        }
-                    });
-                    build_tokens.push(quote! {
-                        let #field_name = match self.#field_name {
-                            Some(ref #field_name) => #field_name.clone(),
-                            None => return Err(#msg.to_string().into()),
-                        };
-                    });
                 }
+                std::result::Result::Ok(builder)
             }
-            _ => panic!("expected TypePath"),
-        };
+
+            /// The description of the options `parse_args()` accepts.
+            #builder_vis fn help() -> &'static str {
+                #help
+            }
+        }
+
+        /// Why `parse_args()` did not return a builder.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #builder_vis enum #args_error_name {
+            /// `--help` was given; holds the text to print.
+            Help(std::string::String),
+            /// An argument which is not one of the options.
+            UnknownArgument(std::string::String),
+            /// An option given without its value.
+            MissingValue(&'static str),
+            /// An option whose value could not be parsed.
+            InvalidValue {
+                option: &'static str,
+                value: std::string::String,
+                message: std::string::String,
+            },
+        }
+
+        impl std::fmt::Display for #args_error_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #args_error_name::Help(help) => f.write_str(help),
+                    #args_error_name::UnknownArgument(arg) => write!(f, "unknown argument '{}'", arg),
+                    #args_error_name::MissingValue(option) => write!(f, "'{}' needs a value", option),
+                    #args_error_name::InvalidValue { option, value, message } => {
+                        write!(f, "invalid value '{}' for '{}': {}", value, option, message)
+                    }
+                }
+            }
+        }
+
+        impl std::error::Error for #args_error_name {}
     }
+}
 
-    let tokens = quote!(
-        #[derive(Default)]
-        pub struct #builder_name {
-            #(#declr_tokens)*
+/// Statements storing in `builder` what is parsed through `FromStr` from
+/// `value`, a `&str`, or returning the error which `error` makes from the
+/// string that did not parse and a message. A collection is extended with
+/// one item, or with `split` with each of the comma separated items, and the
+/// entries of a map are written as `key=value`. `None` for a field which is
+/// not parsed.
+fn store_from_str(
+    field: &BuilderField,
+    split: bool,
+    error: &dyn Fn(proc_macro2::TokenStream, proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+) -> Option<proc_macro2::TokenStream> {
+    let field_name = &field.name;
+    let parse = |ty: &syn::Type, value: proc_macro2::TokenStream| {
+        let error = error(
+            value.clone(),
+            quote!(std::string::ToString::to_string(&err)),
+        );
+        quote! {
+            match <#ty as std::str::FromStr>::from_str(#value) {
+                std::result::Result::Ok(parsed) => parsed,
+                std::result::Result::Err(err) => return std::result::Result::Err(#error),
+            }
+        }
+    };
+    let (parsed, extend) = match field.kind {
+        FieldKind::Optional(ref value_type) => {
+            let parsed = parse(value_type, quote!(value));
+            return Some(quote!(builder.#field_name = std::option::Option::Some(#parsed);));
+        }
+        FieldKind::Required => {
+            let parsed = parse(field.ty, quote!(value));
+            return Some(quote!(builder.#field_name = std::option::Option::Some(#parsed);));
+        }
+        FieldKind::Repeated {
+            item: EachItem::Element(ref element_type),
+            ..
+        } => (parse(element_type, quote!(item)), quote!()),
+        FieldKind::Repeated {
+            item: EachItem::Entry(ref key_type, ref value_type),
+            ..
+        } => {
+            let parsed_key = parse(key_type, quote!(key.trim()));
+            let parsed_value = parse(value_type, quote!(entry_value.trim()));
+            let missing = error(
+                quote!(item),
+                quote!(std::string::String::from("expected `key=value`")),
+            );
+            (
+                quote!((#parsed_key, #parsed_value)),
+                quote! {
+                    let (key, entry_value) = match item.find('=') {
+                        std::option::Option::Some(index) => (&item[..index], &item[index + 1..]),
+                        std::option::Option::None => return std::result::Result::Err(#missing),
+                    };
+                },
+            )
+        }
+        // Rejected with an error by `builder_field`, or not stored.
+        FieldKind::Repeated { .. } | FieldKind::SubBuilder { .. } | FieldKind::Skipped => {
+            return None;
+        }
+    };
+    let items = if split {
+        quote!(value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty()))
+    } else {
+        quote!(std::iter::once(value))
+    };
+    Some(quote! {
+        for item in #items {
+            #extend
+            let parsed = #parsed;
+            std::iter::Extend::extend(&mut builder.#field_name, std::iter::once(parsed));
         }
+    })
+}
 
-        impl #builder_name {
-            #(#fn_tokens)*
+/// The generic parameters, parameters and stored value of the setter of a
+/// field holding a `value_type`, or an `Option` of one.
+fn value_setter(
+    field: &BuilderField,
+    value_type: &syn::Type,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+) {
+    let field_name = &field.name;
+    if let Some(ref transform) = field.transform {
+        let inputs = &transform.inputs;
+        let body = &transform.body;
+        return (quote!(), quote!(#inputs), quote!({ #body }));
+    }
+    if let Some((ref new, ref wrapped)) = field.wrap {
+        // A trait object is boxed from any value implementing the trait.
+        if let syn::Type::TraitObject(syn::TypeTraitObject { ref bounds, .. }) = wrapped {
+            let lifetime = if bounds
+                .iter()
+                .any(|bound| matches!(bound, syn::TypeParamBound::Lifetime(_)))
+            {
+                quote!()
+            } else {
+                quote!(+ 'static)
+            };
+            return (
+                quote!(<__W: #bounds #lifetime>),
+                quote!(#field_name: __W),
+                quote!(#new(#field_name)),
+            );
+        }
+        return (
+            quote!(),
+            quote!(#field_name: #wrapped),
+            quote!(#new(#field_name)),
+        );
+    }
+    let (generics, arg_type, value) = setter_arg(field_name, value_type, field.into, "__V");
+    (generics, quote!(#field_name: #arg_type), value)
+}
 
-            pub fn build(&self) -> std::result::Result<#struct_name, std::boxed::Box<dyn std::error::Error>> {
-                #(#build_tokens)*
+/// The generic parameters, argument type and stored value for a setter whose
+/// argument is `arg` of `value_type`, which with `setter(into)` accepts
+/// anything convertible into `value_type` through a generic parameter named
+/// `param`.
+fn setter_arg(
+    arg: &Ident,
+    value_type: &syn::Type,
+    into: bool,
+    param: &str,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+) {
+    if into {
+        let param = Ident::new(param, Span::call_site());
+        (
+            quote!(<#param: std::convert::Into<#value_type>>),
+            quote!(#param),
+            quote!(std::convert::Into::into(#arg)),
+        )
+    } else {
+        (quote!(), quote!(#value_type), quote!(#arg))
+    }
+}
 
-                Ok(#struct_name{#(#field_name_tokens)*})
+/// The lines of the doc comment in `attrs`, trimmed and joined with spaces.
+fn doc_comment(attrs: &[syn::Attribute]) -> String {
+    let mut lines = vec![];
+    for attr in attrs.iter().filter(|a| a.path.is_ident("doc")) {
+        if let Ok(syn::Meta::NameValue(syn::MetaNameValue {
+            lit: syn::Lit::Str(ref lit_str),
+            ..
+        })) = attr.parse_meta()
+        {
+            let line = lit_str.value();
+            if !line.trim().is_empty() {
+                lines.push(line.trim().to_owned());
             }
         }
+    }
+    lines.join(" ")
+}
+
+/// The identifier in a string literal such as the `"arg"` of `each = "arg"`.
+fn parse_ident(lit_str: &syn::LitStr) -> syn::Result<Ident> {
+    lit_str
+        .parse()
+        .map_err(|_| syn::Error::new_spanned(lit_str, "expected an identifier"))
+}
+
+fn segments_match_tail(
+    segments: &syn::punctuated::Punctuated<syn::PathSegment, syn::token::Colon2>,
+    names: &[&str],
+) -> bool {
+    if !segments.is_empty() && segments.len() <= names.len() {
+        let start = names.len() - segments.len();
+        segments
+            .iter()
+            .map(|s| &s.ident)
+            .zip(names[start..].iter())
+            .all(|(a, b)| a == b)
+    } else {
+        false
+    }
+}
+
+fn is_bool(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(syn::TypePath { qself: None, ref path }) if path.is_ident("bool"))
+}
+
+fn is_phantom_data(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        }) => {
+            segments_match_tail(&path.segments, &["std", "marker", "PhantomData"])
+                || segments_match_tail(&path.segments, &["core", "marker", "PhantomData"])
+        }
+        _ => false,
+    }
+}
 
-        impl #struct_name {
-            pub fn builder() -> #builder_name {
-                #builder_name::default()
+/// A name such as `KeyPress` in snake case, `key_press`. A run of capitals is one word, so `HTTPError`
+/// becomes `http_error`, its last capital starting the next word.
+fn to_snake_case(camel: &str) -> String {
+    let chars: Vec<char> = camel.chars().collect();
+    let mut snake = String::new();
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch.is_uppercase() {
+            let after_lower = i > 0 && !chars[i - 1].is_uppercase() && chars[i - 1] != '_';
+            let ends_run = i > 0
+                && chars[i - 1].is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_lower || ends_run {
+                snake.push('_');
             }
+            snake.extend(ch.to_lowercase());
+        } else {
+            snake.push(ch);
         }
-    );
+    }
+    snake
+}
 
-    TokenStream::from(tokens)
+fn to_camel_case(snake: &str) -> String {
+    snake
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
 }
@@ -0,0 +1,35 @@
+// With #[builder(pattern = "owned")] on the struct, the setters take and return
+// the builder by value and build() consumes it, moving each field into the
+// struct. Nothing needs to be Clone, so fields such as boxed closures can be
+// built. The default, #[builder(pattern = "mutable")], keeps the &mut Self
+// setters used by the earlier tests.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    on_exit: Box<dyn Fn(i32) -> bool>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .arg("--release".to_owned())
+        .on_exit(Box::new(|code| code == 0))
+        .build()
+        .unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert!(command.current_dir.is_none());
+    assert!((command.on_exit)(0));
+
+    let builder = Command::builder().executable("cargo".to_owned());
+    assert!(builder.build().is_err());
+}
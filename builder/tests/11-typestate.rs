@@ -0,0 +1,35 @@
+// With #[builder(typestate)] the builder records in its type which required
+// fields have been set. Each required field is a type parameter on the
+// builder, `()` until its setter is called and `(T,)` afterwards, and build()
+// is only implemented once all of them are set. Since nothing can be missing
+// at that point, build() returns the struct itself rather than a Result.
+//
+// Optional and repeated fields do not affect the type and can be set any
+// number of times, in any order.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let command: Command = Command::builder()
+        .arg("build".to_owned())
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .env(vec![])
+        .arg("--release".to_owned())
+        .build();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert!(command.env.is_empty());
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+}
@@ -0,0 +1,17 @@
+// A typestate builder turns a forgotten required field into a compile error:
+// without a call to env(), build() does not exist for the builder's type.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    env: Vec<String>,
+}
+
+fn main() {
+    let _command = Command::builder()
+        .executable("cargo".to_owned())
+        .build();
+}
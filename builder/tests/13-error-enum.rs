@@ -0,0 +1,42 @@
+// Alongside the builder the macro generates a `<Builder>Error` enum, so
+// callers can match on why build() failed. Every required field that has not
+// been set is reported together in a single UninitializedFields error rather
+// than stopping at the first one.
+//
+// The names of the generated code's own variables cannot clash with those of
+// the fields, such as the `uninitialized_fields` here.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: String,
+    args: Vec<String>,
+    env: Vec<String>,
+    current_dir: Option<String>,
+    uninitialized_fields: Option<Vec<String>>,
+}
+
+fn main() {
+    let err = Command::builder().args(vec![]).build().unwrap_err();
+    match err {
+        CommandBuilderError::UninitializedFields(ref fields) => {
+            assert_eq!(*fields, vec!["executable", "env"]);
+        }
+        CommandBuilderError::NestedUninitializedFields(_) => unreachable!(),
+        CommandBuilderError::Validation(_) => unreachable!(),
+        CommandBuilderError::Constraint(_) => unreachable!(),
+    }
+    assert_eq!(
+        err.to_string(),
+        "'executable', 'env' fields have not been set",
+    );
+
+    let err: Box<dyn std::error::Error> = Command::builder()
+        .executable("cargo".to_owned())
+        .args(vec![])
+        .build()
+        .unwrap_err()
+        .into();
+    assert_eq!(err.to_string(), "'env' field has not been set");
+}
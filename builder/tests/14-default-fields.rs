@@ -0,0 +1,84 @@
+// Fields marked #[builder(default)] no longer need to be set before build();
+// an unset field takes its type's Default value, or the value of the given
+// expression with #[builder(default = "...")].
+//
+// Putting #[builder(default)] on the struct instead fills every unset field
+// from the struct's own Default impl. A field-level default still takes
+// precedence over the struct-level one.
+//
+// The struct-level default moves each unset field out of a single Default
+// value of the struct, so it cannot be used on a struct that implements Drop,
+// whose fields cannot be moved out; give the fields of such a struct their
+// own #[builder(default)] instead.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(default)]
+    env: Vec<String>,
+    #[builder(default = "String::from(\".\")")]
+    current_dir: String,
+    #[builder(default = "Some(30)")]
+    timeout: Option<u32>,
+}
+
+#[derive(Builder)]
+#[builder(default)]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(default = "8")]
+    workers: usize,
+    tls: Option<bool>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            workers: 1,
+            tls: Some(false),
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    #[builder(default = "3")]
+    retries: u32,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .build()
+        .unwrap();
+    assert!(command.env.is_empty());
+    assert_eq!(command.current_dir, ".");
+    assert_eq!(command.timeout, Some(30));
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .timeout(5)
+        .build()
+        .unwrap();
+    assert_eq!(command.current_dir, "..");
+    assert_eq!(command.timeout, Some(5));
+
+    let server = Server::builder().port(443).build().unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 443);
+    assert_eq!(server.workers, 8);
+    assert_eq!(server.tls, Some(false));
+
+    let job = Job::builder().name("backup".to_owned()).build();
+    assert_eq!(job.retries, 3);
+    let job = Job::builder().retries(0).name("backup".to_owned()).build();
+    assert_eq!(job.retries, 0);
+}
@@ -0,0 +1,50 @@
+// With #[builder(setter(into))] a setter is generic over its argument and
+// accepts anything that converts Into the field's type, so callers can pass
+// string literals to String fields. The one-at-a-time setter of an `each`
+// field accepts anything Into the element type.
+//
+// On the struct, setter(into) applies to every field.
+
+use derive_builder::Builder;
+use std::path::PathBuf;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(setter(into))]
+    executable: String,
+    #[builder(each = "arg", setter(into))]
+    args: Vec<String>,
+    #[builder(setter(into))]
+    current_dir: Option<PathBuf>,
+    timeout: u64,
+}
+
+#[derive(Builder)]
+#[builder(setter(into), typestate)]
+pub struct Job {
+    name: String,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+    owner: Option<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo")
+        .arg("build")
+        .arg(String::from("--release"))
+        .current_dir("..")
+        .timeout(30)
+        .build()
+        .unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir, Some(PathBuf::from("..")));
+    assert_eq!(command.timeout, 30);
+
+    let job = Job::builder().tag("nightly").name("backup").owner("ops").build();
+    assert_eq!(job.name, "backup");
+    assert_eq!(job.tags, vec!["nightly"]);
+    assert_eq!(job.owner.as_deref(), Some("ops"));
+}
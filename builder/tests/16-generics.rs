@@ -0,0 +1,68 @@
+// The builder carries over the struct's lifetimes, type parameters, const
+// parameters and where clause, on the builder struct itself, its impl and
+// the `builder()` constructor. The generated builder implements Default by
+// hand so that the type parameters do not need to be Default.
+
+use derive_builder::Builder;
+use std::fmt::Display;
+
+pub trait Body {
+    fn len(&self) -> usize;
+}
+
+#[derive(Clone)]
+pub struct Text(String);
+
+impl Body for Text {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[derive(Builder)]
+pub struct Request<'a, B: Body, const N: usize>
+where
+    B: Clone,
+{
+    method: &'a str,
+    body: B,
+    #[builder(each = "header")]
+    headers: Vec<(&'a str, String)>,
+    ports: [u16; N],
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Labelled<T: Display, U = u32> {
+    label: T,
+    extra: Option<U>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Pair<'a, T> {
+    first: &'a T,
+    second: &'a T,
+}
+
+fn main() {
+    let request = Request::builder()
+        .method("GET")
+        .body(Text("hello".to_owned()))
+        .header(("Accept", "text/plain".to_owned()))
+        .ports([80, 443])
+        .build()
+        .unwrap();
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.body.len(), 5);
+    assert_eq!(request.headers.len(), 1);
+    assert_eq!(request.ports, [80, 443]);
+
+    let labelled: Labelled<&str> = Labelled::builder().label("x").build().unwrap();
+    assert_eq!(labelled.label.to_string(), "x");
+    assert!(labelled.extra.is_none());
+
+    let (a, b) = (1, 2);
+    let pair = Pair::builder().second(&b).first(&a).build();
+    assert_eq!(*pair.first + *pair.second, 3);
+}
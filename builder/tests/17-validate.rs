@@ -0,0 +1,63 @@
+// With #[builder(build_fn(validate = "path::to::fn"))] the generated build()
+// first passes the builder to the named function, which can check
+// relationships between fields. An Err(String) from it is returned as the
+// Validation variant of the builder's error type.
+//
+// Validation runs before required fields are checked, so the validator sees
+// unset fields as None.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(build_fn(validate = "checks::validate_command"))]
+pub struct Command {
+    executable: String,
+    #[builder(each = "env")]
+    env: Vec<String>,
+    current_dir: Option<String>,
+    port: u16,
+}
+
+mod checks {
+    use super::CommandBuilder;
+
+    pub fn validate_command(builder: &CommandBuilder) -> Result<(), String> {
+        if let Some(port) = builder.port {
+            if port < 1024 {
+                return Err(format!("port {} is reserved", port));
+            }
+        }
+        if !builder.env.is_empty() && builder.current_dir.is_none() {
+            return Err("current_dir must be set when env is not empty".to_owned());
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .port(8080)
+        .build()
+        .unwrap();
+    assert_eq!(command.port, 8080);
+
+    let err = Command::builder()
+        .executable("cargo".to_owned())
+        .port(80)
+        .build()
+        .unwrap_err();
+    assert_eq!(err, CommandBuilderError::Validation("port 80 is reserved".to_owned()));
+    assert_eq!(err.to_string(), "port 80 is reserved");
+
+    let err = Command::builder()
+        .executable("cargo".to_owned())
+        .env("RUST_LOG=debug".to_owned())
+        .port(8080)
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, CommandBuilderError::Validation(_)));
+
+    let err = Command::builder().port(8080).build().unwrap_err();
+    assert_eq!(err, CommandBuilderError::UninitializedFields(vec!["executable"]));
+}
@@ -0,0 +1,68 @@
+// Deriving Builder on an enum generates one builder per variant with named
+// fields. The builder for `Event::Click` is `EventClickBuilder`, created by
+// `Event::click_builder()`, and its build() returns an `Event`. Field
+// handling is the same as for structs; unit and tuple variants get no
+// builder. An acronym in a variant name is kept as one word, so `HTTPError`
+// is built by `http_error_builder()`.
+
+use derive_builder::Builder;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mod {
+    Shift,
+    Ctrl,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub enum Event {
+    Click {
+        x: i32,
+        y: i32,
+    },
+    KeyPress {
+        code: u32,
+        #[builder(each = "modifier")]
+        modifiers: Vec<Mod>,
+        repeat: Option<u32>,
+    },
+    HTTPError {
+        status: u16,
+    },
+    Resize(u32, u32),
+    Quit,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(typestate, setter(into))]
+pub enum Message {
+    Text { body: String },
+}
+
+fn main() {
+    let click = Event::click_builder().x(10).y(20).build().unwrap();
+    assert_eq!(click, Event::Click { x: 10, y: 20 });
+
+    let key = Event::key_press_builder()
+        .code(65)
+        .modifier(Mod::Shift)
+        .modifier(Mod::Ctrl)
+        .build()
+        .unwrap();
+    assert_eq!(
+        key,
+        Event::KeyPress {
+            code: 65,
+            modifiers: vec![Mod::Shift, Mod::Ctrl],
+            repeat: None,
+        }
+    );
+
+    let error = Event::http_error_builder().status(404).build().unwrap();
+    assert_eq!(error, Event::HTTPError { status: 404 });
+
+    let err = Event::click_builder().x(1).build().unwrap_err();
+    assert_eq!(err, EventClickBuilderError::UninitializedFields(vec!["y"]));
+
+    let text = Message::text_builder().body("hi").build();
+    assert_eq!(text, Message::Text { body: "hi".to_owned() });
+}
@@ -0,0 +1,39 @@
+// Tuple structs get positional setters. Each field's setter is named by a
+// #[builder(name = "...")] attribute on the field, falling back to `field0`,
+// `field1` and so on by position, and build() produces the tuple struct.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Rgb(
+    #[builder(name = "red")] u8,
+    #[builder(name = "green")] u8,
+    #[builder(name = "blue", default)] u8,
+);
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Labels(String, #[builder(each = "label")] Vec<String>, Option<u32>);
+
+fn main() {
+    let rgb = Rgb::builder().red(255).green(128).build().unwrap();
+    assert_eq!(rgb, Rgb(255, 128, 0));
+
+    let err = Rgb::builder().green(1).build().unwrap_err();
+    assert_eq!(err, RgbBuilderError::UninitializedFields(vec!["red"]));
+
+    let labels = Labels::builder()
+        .field0("colours".to_owned())
+        .label("warm".to_owned())
+        .label("cold".to_owned())
+        .field2(3)
+        .build()
+        .unwrap();
+    assert_eq!(
+        labels,
+        Labels(
+            "colours".to_owned(),
+            vec!["warm".to_owned(), "cold".to_owned()],
+            Some(3),
+        )
+    );
+}
@@ -0,0 +1,58 @@
+// The names of the generated items can be changed:
+//
+//   - #[builder(name = "...")] on the struct names the builder type, and its
+//     error type becomes the same name followed by `Error`;
+//   - #[builder(setter(prefix = "with"))] on the struct or a field puts
+//     `with_` in front of the field name to name its setter;
+//   - #[builder(setter(name = "..."))] on a field names its setter outright;
+//   - #[builder(build_fn(name = "..."))] on the struct renames build().
+//
+// Setters named explicitly by `each` are not prefixed.
+
+use derive_builder::Builder;
+
+pub struct CommandBuilder;
+
+#[derive(Builder, Debug)]
+#[builder(
+    name = "CommandOptions",
+    setter(prefix = "with"),
+    build_fn(name = "finish")
+)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(setter(name = "cwd"))]
+    current_dir: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(typestate, build_fn(name = "done"))]
+pub struct Job {
+    #[builder(setter(prefix = "set"))]
+    name: String,
+}
+
+fn main() {
+    let _unrelated = CommandBuilder;
+
+    let mut options: CommandOptions = Command::builder();
+    let command = options
+        .with_executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .with_args(vec!["--release".to_owned()])
+        .cwd("..".to_owned())
+        .finish()
+        .unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    let err: CommandOptionsError = Command::builder().finish().unwrap_err();
+    assert_eq!(err.to_string(), "'executable' field has not been set");
+
+    let job = Job::builder().set_name("backup".to_owned()).done();
+    assert_eq!(job.name, "backup");
+}
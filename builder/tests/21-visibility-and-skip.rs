@@ -0,0 +1,57 @@
+// The builder type, its error type and its methods take the visibility of
+// the struct unless #[builder(vis = "...")] on the struct says otherwise.
+// The same attribute on a field sets the visibility of that field's setters.
+//
+// A field with #[builder(setter(skip))] has no setter and no storage in the
+// builder; build() always initializes it from its default. PhantomData
+// fields are skipped automatically.
+
+use derive_builder::Builder;
+
+mod config {
+    use derive_builder::Builder;
+    use std::marker::PhantomData;
+
+    #[derive(Builder)]
+    #[builder(vis = "pub(crate)")]
+    pub struct Command<T> {
+        pub executable: String,
+        #[builder(vis = "")]
+        pub secret: Option<String>,
+        #[builder(setter(skip))]
+        pub runs: u32,
+        #[builder(setter(skip), default = "\"internal\".to_owned()")]
+        pub origin: String,
+        pub marker: PhantomData<T>,
+    }
+
+    impl<T> CommandBuilder<T> {
+        pub fn with_default_secret(&mut self) -> &mut Self {
+            self.secret("hunter2".to_owned())
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+struct Job {
+    name: String,
+    #[builder(setter(skip))]
+    attempts: Vec<u32>,
+}
+
+fn main() {
+    let command = config::Command::<u8>::builder()
+        .executable("cargo".to_owned())
+        .with_default_secret()
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.secret.as_deref(), Some("hunter2"));
+    assert_eq!(command.runs, 0);
+    assert_eq!(command.origin, "internal");
+
+    let job = Job::builder().name("backup".to_owned()).build();
+    assert_eq!(job.name, "backup");
+    assert!(job.attempts.is_empty());
+}
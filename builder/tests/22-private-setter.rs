@@ -0,0 +1,20 @@
+// A setter made private with #[builder(vis = "")] cannot be called from
+// outside the module that defines the struct.
+
+mod config {
+    use derive_builder::Builder;
+
+    #[derive(Builder)]
+    pub struct Command {
+        pub executable: String,
+        #[builder(vis = "")]
+        pub secret: Option<String>,
+    }
+}
+
+fn main() {
+    let _ = config::Command::builder()
+        .executable("cargo".to_owned())
+        .secret("hunter2".to_owned())
+        .build();
+}
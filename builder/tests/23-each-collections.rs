@@ -0,0 +1,66 @@
+// The `each` attribute is not limited to Vec. It works on any collection that
+// implements Default and Extend, so sets and maps can be built up one item at
+// a time too. The one-at-a-time setter of a map takes the key and the value
+// as two separate arguments.
+//
+// The setter named after the field extends the collection with anything that
+// can be iterated over, not only a value of the field's own type.
+//
+// A collection need not even be iterable itself: here `Counter` implements
+// nothing but Default, Clone and Extend.
+
+use derive_builder::Builder;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+#[derive(Default, Clone)]
+pub struct Counter(u32);
+
+impl Extend<u8> for Counter {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        for n in iter {
+            self.0 += u32::from(n);
+        }
+    }
+}
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: VecDeque<String>,
+    #[builder(each = "env", setter(into))]
+    env: HashMap<String, String>,
+    #[builder(each = "feature")]
+    features: BTreeSet<&'static str>,
+    #[builder(each = "limit")]
+    limits: BTreeMap<&'static str, u64>,
+    #[builder(each = "tick")]
+    ticks: Counter,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .args(vec!["--release".to_owned(), "--locked".to_owned()])
+        .env("RUST_LOG", "debug")
+        .env("CARGO_HOME", "/tmp/cargo")
+        .feature("serde")
+        .features(["std", "serde"].iter().copied())
+        .limits(Some(("cpu", 4)))
+        .limit("memory", 1024)
+        .tick(3u8)
+        .ticks(vec![1u8, 2])
+        .build()
+        .unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, ["build", "--release", "--locked"]);
+    assert_eq!(command.env.len(), 2);
+    assert_eq!(command.env["RUST_LOG"], "debug");
+    assert_eq!(command.env["CARGO_HOME"], "/tmp/cargo");
+    assert_eq!(command.features.into_iter().collect::<Vec<_>>(), ["serde", "std"]);
+    assert_eq!(command.limits["cpu"], 4);
+    assert_eq!(command.limits["memory"], 1024);
+    assert_eq!(command.ticks.0, 6);
+}
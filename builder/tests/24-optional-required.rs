@@ -0,0 +1,58 @@
+// Fields are recognized as optional whether their type is written as Option,
+// std::option::Option or core::option::Option, and `each` works just as well
+// on a fully qualified std::vec::Vec.
+//
+// The macro only sees the tokens of a field's type, so it cannot tell that a
+// type alias stands for an Option. Mark such a field #[builder(optional)]:
+// its setter takes the field's type whole and build() leaves it at its
+// Default when never called. Conversely #[builder(required)] makes build()
+// fail unless the setter of an Option field was called, the setter taking the
+// whole Option.
+
+use derive_builder::Builder;
+use std::path::PathBuf;
+
+type MaybeDir = Option<PathBuf>;
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: std::vec::Vec<String>,
+    env: std::option::Option<Vec<String>>,
+    nice: ::core::option::Option<i32>,
+    #[builder(optional)]
+    current_dir: MaybeDir,
+    #[builder(required)]
+    timeout: Option<u64>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .nice(10)
+        .timeout(None)
+        .build()
+        .unwrap();
+    assert_eq!(command.args, vec!["build".to_owned()]);
+    assert_eq!(command.env, None);
+    assert_eq!(command.nice, Some(10));
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.timeout, None);
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir(Some("..".into()))
+        .timeout(Some(60))
+        .build()
+        .unwrap();
+    assert_eq!(command.current_dir, Some(PathBuf::from("..")));
+    assert_eq!(command.timeout, Some(60));
+
+    let err = Command::builder()
+        .executable("cargo".to_owned())
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "'timeout' field has not been set");
+}
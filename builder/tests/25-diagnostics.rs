@@ -0,0 +1,37 @@
+// Every mistake in the input of one derive is reported at once, each pointing
+// at the tokens responsible, instead of the macro panicking at the first.
+//
+// Here the builder attributes are malformed or misspelt, `each` is given
+// field types that are not collections, and the derive is applied to a unit
+// struct and a union, which have no fields for a builder to set.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(pattern = "borrowed")]
+#[builder]
+pub struct Command {
+    #[builder]
+    executable: String,
+    #[builder(each = "not an ident")]
+    args: Vec<String>,
+    #[builder(each = "dir", defualt)]
+    dirs: Vec<String>,
+    #[builder(each = "env")]
+    env: Option<Vec<String>>,
+    #[builder(each = "limit")]
+    limits: (u64, u64),
+    #[builder(each = "path")]
+    paths: &'static [&'static str],
+}
+
+#[derive(Builder)]
+pub struct Unit;
+
+#[derive(Builder)]
+pub union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,48 @@
+// Besides its setter, an optional field gets a setter with an `_opt` suffix
+// taking the whole Option, so a value that may be absent can be forwarded
+// without matching on it, and a `clear_` method setting it back to None.
+// The `_opt` setter takes an Option of the field's own type even with
+// setter(into), so that a plain None needs no type annotation.
+//
+// A field with `each` gets a `clear_` method too, which empties the
+// collection again.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(setter(into))]
+    current_dir: Option<String>,
+    timeout: Option<u64>,
+}
+
+fn main() {
+    let inherited_dir: Option<String> = Some("/tmp".to_owned());
+
+    let mut builder = Command::builder();
+    builder
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .current_dir_opt(inherited_dir)
+        .timeout(60);
+    let command = builder.build().unwrap();
+    assert_eq!(command.args, vec!["build".to_owned()]);
+    assert_eq!(command.current_dir.as_deref(), Some("/tmp"));
+    assert_eq!(command.timeout, Some(60));
+
+    builder
+        .clear_args()
+        .arg("test".to_owned())
+        .current_dir_opt(None)
+        .clear_timeout();
+    let command = builder.build().unwrap();
+    assert_eq!(command.args, vec!["test".to_owned()]);
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.timeout, None);
+
+    let command = builder.timeout_opt(Some(5)).build().unwrap();
+    assert_eq!(command.timeout, Some(5));
+}
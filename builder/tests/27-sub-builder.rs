@@ -0,0 +1,113 @@
+// A field whose type also derives Builder can be marked
+// #[builder(sub_builder)]. The outer builder then holds the field's own
+// builder and its setter hands out a mutable reference to it, so the nested
+// value is configured in place and built by the outer build().
+//
+// Required fields missing from a nested builder are reported by the outer
+// builder's error, named by their path from the outer struct, through a
+// NestedUninitializedFields variant alongside the UninitializedFields one.
+// missing_field_paths() names them the same way, while missing_fields() names
+// the sub-builder field itself. A sub-builder field has no is_<field>_set()
+// method, since it is never set as a whole.
+//
+// A nested builder may use the owned pattern, as long as the outer builder
+// does too: the outer build(&self) of the mutable pattern cannot move the
+// nested builder out to build it.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct TlsConfig {
+    cert_path: String,
+    key_path: String,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct PoolConfig {
+    max_connections: u32,
+    idle_timeout: Option<u64>,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct ServerConfig {
+    host: String,
+    // Named like a variable of the generated build(), which must not clash.
+    nested_uninitialized_fields: Option<u32>,
+    #[builder(sub_builder)]
+    tls: TlsConfig,
+    #[builder(sub_builder)]
+    pool: PoolConfig,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "owned")]
+pub struct Retry {
+    attempts: u32,
+    backoff: Option<u64>,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "owned")]
+pub struct Job {
+    name: String,
+    #[builder(sub_builder)]
+    retry: Retry,
+}
+
+fn main() {
+    let mut builder = ServerConfig::builder();
+    builder.host("localhost".to_owned());
+    builder
+        .tls()
+        .cert_path("cert.pem".to_owned())
+        .key_path("key.pem".to_owned());
+    builder.pool().max_connections(16);
+
+    let config = builder.build().unwrap();
+    assert_eq!(
+        config.tls,
+        TlsConfig {
+            cert_path: "cert.pem".to_owned(),
+            key_path: "key.pem".to_owned(),
+        }
+    );
+    assert_eq!(config.pool.max_connections, 16);
+    assert_eq!(config.pool.idle_timeout, None);
+
+    let mut builder = ServerConfig::builder();
+    builder.tls().key_path("key.pem".to_owned());
+    assert_eq!(builder.missing_fields(), vec!["host", "tls", "pool"]);
+    assert_eq!(
+        builder.missing_field_paths(),
+        vec!["host", "tls.cert_path", "pool.max_connections"]
+    );
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err,
+        ServerConfigBuilderError::NestedUninitializedFields(vec![
+            "host".to_owned(),
+            "tls.cert_path".to_owned(),
+            "pool.max_connections".to_owned(),
+        ])
+    );
+    assert_eq!(
+        err.to_string(),
+        "'host', 'tls.cert_path', 'pool.max_connections' fields have not been set"
+    );
+
+    let mut defaults = Job::builder().name("backup".to_owned());
+    *defaults.retry() = Retry::builder().attempts(1).backoff(100);
+    let mut overrides = Job::builder();
+    *overrides.retry() = Retry::builder().attempts(3);
+    let job = defaults.merge(overrides).build().unwrap();
+    assert_eq!(
+        job.retry,
+        Retry {
+            attempts: 3,
+            backoff: Some(100),
+        }
+    );
+
+    let err = Job::builder().name("backup".to_owned()).build().unwrap_err();
+    assert_eq!(err.to_string(), "'retry.attempts' field has not been set");
+}
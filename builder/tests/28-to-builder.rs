@@ -0,0 +1,57 @@
+// A built value can be turned back into a builder with every field already
+// set from it, to change a field and build again. `From<Command>` is
+// implemented for the builder, and with the default mutable pattern, whose
+// build() already requires the fields to be Clone, `Command::to_builder(&self)`
+// copies them out of a borrowed value.
+//
+// The rebuilt value goes through build() again, so its checks still apply.
+// A typestate builder converted from a value is in the state where every
+// required field has been set.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    retries: u32,
+    schedule: Option<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+
+    let release = command
+        .to_builder()
+        .arg("--release".to_owned())
+        .current_dir("..".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(release.executable, "cargo");
+    assert_eq!(release.args, vec!["build", "--release"]);
+    assert_eq!(release.current_dir.as_deref(), Some(".."));
+
+    let rebuilt = CommandBuilder::from(command).clear_args().build().unwrap();
+    assert_eq!(rebuilt.executable, "cargo");
+    assert!(rebuilt.args.is_empty());
+    assert_eq!(rebuilt.current_dir, None);
+
+    let job = Job::builder().name("nightly".to_owned()).retries(1).build();
+    let builder: JobBuilder<(String,), (u32,)> = job.into();
+    let job = builder.schedule("0 3 * * *".to_owned()).build();
+    assert_eq!(job.name, "nightly");
+    assert_eq!(job.retries, 1);
+    assert_eq!(job.schedule.as_deref(), Some("0 3 * * *"));
+}
@@ -0,0 +1,61 @@
+// Configuration is often assembled in layers, such as defaults, then a file,
+// then command line flags, each filling in part of a builder. The generated
+// merge() method takes every field set in another builder, overriding this
+// one's, so the layers can be combined before building.
+//
+// The items of an `each` collection are appended to this builder's by
+// default; with #[builder(merge = "replace")] a layer that has any items
+// replaces them instead. A collection whose item type the macro cannot see,
+// one that only implements Default and Extend, is always replaced by a layer
+// which has been given any items.
+
+use derive_builder::Builder;
+
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Counter(u32);
+
+impl Extend<u8> for Counter {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        for n in iter {
+            self.0 += u32::from(n);
+        }
+    }
+}
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(each = "env", merge = "replace")]
+    env: Vec<String>,
+    current_dir: Option<String>,
+    #[builder(each = "tick")]
+    ticks: Counter,
+}
+
+fn main() {
+    let mut defaults = Command::builder();
+    defaults
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .env("RUST_LOG=info".to_owned())
+        .current_dir("/".to_owned())
+        .tick(1u8)
+        .tick(2u8);
+
+    let mut file = Command::builder();
+    file.arg("--locked".to_owned())
+        .env("RUST_LOG=debug".to_owned())
+        .env("RUST_BACKTRACE=1".to_owned());
+
+    let mut flags = Command::builder();
+    flags.executable("cross".to_owned()).tick(5u8);
+
+    let command = defaults.merge(file).merge(flags).build().unwrap();
+    assert_eq!(command.executable, "cross");
+    assert_eq!(command.args, vec!["build", "--locked"]);
+    assert_eq!(command.env, vec!["RUST_LOG=debug", "RUST_BACKTRACE=1"]);
+    assert_eq!(command.current_dir.as_deref(), Some("/"));
+    assert_eq!(command.ticks, Counter(5));
+}
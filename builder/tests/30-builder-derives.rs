@@ -0,0 +1,43 @@
+// Traits listed in #[builder(derive(...))] are derived on the generated
+// builder, so a half-filled builder can be cloned as a template, compared or
+// printed. Any other attribute can be put on the builder struct with
+// #[builder(struct_attr(...))].
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, Clone, PartialEq)]
+#[builder(derive(Clone, Debug, PartialEq))]
+#[builder(struct_attr(doc = "Builds a `Command`.", must_use))]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate, derive(Debug))]
+pub struct Job {
+    name: String,
+}
+
+fn main() {
+    let mut template = Command::builder();
+    template.executable("cargo".to_owned()).arg("build".to_owned());
+
+    let mut release = template.clone();
+    release.arg("--release".to_owned());
+    assert_ne!(template, release);
+
+    let debug = template.build().unwrap();
+    let release = release.build().unwrap();
+    assert_eq!(debug.args, vec!["build"]);
+    assert_eq!(release.args, vec!["build", "--release"]);
+
+    let printed = format!("{:?}", template);
+    assert!(printed.starts_with("CommandBuilder {"));
+    assert!(printed.contains("\"cargo\""));
+
+    let job = Job::builder().name("nightly".to_owned());
+    assert!(format!("{:?}", job).contains("\"nightly\""));
+}
@@ -0,0 +1,59 @@
+// Before calling build(), a builder can report what it still needs: the
+// generated missing_fields() lists every required field that has not been
+// set, and each field gets an is_<field>_set() method.
+//
+// Optional fields and fields with a default are never missing. A field with
+// `each` counts as set once it has any items, or, for a collection which
+// cannot be iterated, once one of its setters has been called.
+
+use derive_builder::Builder;
+
+#[derive(Default, Clone)]
+pub struct Counter(u32);
+
+impl Extend<u8> for Counter {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        for n in iter {
+            self.0 += u32::from(n);
+        }
+    }
+}
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    timeout: u64,
+    #[builder(default = "3")]
+    retries: u32,
+    #[builder(each = "tick")]
+    ticks: Counter,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    assert_eq!(builder.missing_fields(), vec!["executable", "timeout"]);
+    assert!(!builder.is_executable_set());
+    assert!(!builder.is_args_set());
+    assert!(!builder.is_current_dir_set());
+    assert!(!builder.is_retries_set());
+    assert!(!builder.is_ticks_set());
+
+    builder.executable("cargo".to_owned()).arg("build".to_owned());
+    assert_eq!(builder.missing_fields(), vec!["timeout"]);
+    assert!(builder.is_executable_set());
+    assert!(builder.is_args_set());
+    builder.tick(1);
+    assert!(builder.is_ticks_set());
+    builder.clear_ticks();
+    assert!(!builder.is_ticks_set());
+    assert!(builder.build().is_err());
+
+    builder.timeout(60).current_dir("..".to_owned());
+    assert!(builder.missing_fields().is_empty());
+    assert!(builder.is_timeout_set());
+    assert!(builder.is_current_dir_set());
+    assert!(builder.build().is_ok());
+}
@@ -0,0 +1,48 @@
+// With #[builder(try_setter)] a field also gets a `try_` setter accepting
+// anything that converts TryInto the field's type, which returns the
+// conversion's error instead of the builder when it fails. This saves the
+// conversion boilerplate when filling a builder from loosely typed input.
+//
+// On the struct, try_setter applies to every field it can.
+
+use derive_builder::Builder;
+use std::num::{NonZeroU32, TryFromIntError};
+
+#[derive(Builder)]
+pub struct Server {
+    host: String,
+    #[builder(try_setter)]
+    port: u16,
+    #[builder(try_setter)]
+    workers: Option<NonZeroU32>,
+}
+
+#[derive(Builder)]
+#[builder(typestate, try_setter)]
+pub struct Limits {
+    files: u16,
+    threads: Option<u8>,
+}
+
+fn main() -> Result<(), TryFromIntError> {
+    let from_json: u64 = 8080;
+
+    let server = Server::builder()
+        .host("localhost".to_owned())
+        .try_port(from_json)?
+        .try_workers(4u32)?
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.workers.map(NonZeroU32::get), Some(4));
+
+    assert!(Server::builder().try_port(70000u64).is_err());
+    assert!(Server::builder().try_workers(0u32).is_err());
+
+    let limits = Limits::builder().try_files(1024i64)?.try_threads(8u64)?.build();
+    assert_eq!(limits.files, 1024);
+    assert_eq!(limits.threads, Some(8));
+    assert!(Limits::builder().try_files(-1i64).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+// #[builder(setter(transform = |...| ...))] gives a field's setter the
+// parameters of the closure, and stores the value its body computes from
+// them. Unlike other attribute values the closure is written without quotes.
+//
+// #[builder(setter(wrap))] on a Box, Rc or Arc field, or an Option of one,
+// makes the setter take the value inside and wrap it. For a boxed trait
+// object the setter accepts any value implementing the trait. Since a boxed
+// closure cannot be cloned, this builder uses the owned pattern.
+//
+// With #[builder(try_setter)] on the struct, fields with either setter get
+// no try_ setter, as there is no one type for it to convert into.
+
+use derive_builder::Builder;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+pub struct State {
+    requests: u32,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Command {
+    #[builder(setter(transform = |s: &str| s.to_uppercase()))]
+    executable: String,
+    #[builder(setter(transform = |name: &str, value: &str| format!("{}={}", name, value)))]
+    env: Option<String>,
+    #[builder(setter(wrap))]
+    callback: Box<dyn Fn(&str) -> usize>,
+    #[builder(setter(wrap))]
+    shared: Arc<Mutex<State>>,
+    #[builder(setter(wrap))]
+    label: Option<Rc<String>>,
+    #[builder(setter(wrap))]
+    limit: Option<Box<u64>>,
+}
+
+#[derive(Builder)]
+#[builder(try_setter)]
+pub struct Limits {
+    #[builder(setter(transform = |kib: u32| u64::from(kib) * 1024))]
+    memory: u64,
+    #[builder(setter(wrap))]
+    stack: Box<u64>,
+    files: u16,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo")
+        .env("RUST_LOG", "debug")
+        .callback(|line: &str| line.len())
+        .shared(Mutex::new(State::default()))
+        .limit(10)
+        .build()
+        .unwrap();
+
+    assert_eq!(command.executable, "CARGO");
+    assert_eq!(command.env.as_deref(), Some("RUST_LOG=debug"));
+    assert_eq!((command.callback)("hello"), 5);
+    command.shared.lock().unwrap().requests += 1;
+    assert_eq!(command.shared.lock().unwrap().requests, 1);
+    assert!(command.label.is_none());
+    assert_eq!(command.limit.as_deref(), Some(&10));
+
+    let mut builder = Limits::builder();
+    builder.memory(4).stack(8192).try_files(1024u32).unwrap();
+    let limits = builder.build().unwrap();
+    assert_eq!(limits.memory, 4096);
+    assert_eq!(*limits.stack, 8192);
+    assert_eq!(limits.files, 1024);
+}
@@ -0,0 +1,68 @@
+// With #[builder(from_env)] on the struct, the builder can be created with
+// its fields parsed from strings through FromStr: from_str_map() takes them
+// from a map keyed by field name, and from_env() from environment variables
+// named by a prefix and the field name in upper case, or by the name given
+// with #[builder(env = "...")]. Collections with `each` are given as comma
+// separated items, and maps as `key=value` entries.
+//
+// A string which does not parse is reported with the field it was for.
+
+use derive_builder::Builder;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+#[derive(Builder, Debug)]
+#[builder(from_env, derive(Debug))]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(each = "limit")]
+    limits: BTreeMap<String, u32>,
+    #[builder(env = "TEST_COMMAND_CWD")]
+    current_dir: Option<PathBuf>,
+    timeout: u64,
+}
+
+fn main() {
+    let mut map = HashMap::new();
+    map.insert("executable".to_owned(), "cargo".to_owned());
+    map.insert("args".to_owned(), "build, --release".to_owned());
+    map.insert("limits".to_owned(), "cpu=4,memory=1024".to_owned());
+    map.insert("timeout".to_owned(), "60".to_owned());
+
+    let command = CommandBuilder::from_str_map(&map).unwrap().build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.limits["cpu"], 4);
+    assert_eq!(command.limits["memory"], 1024);
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.timeout, 60);
+
+    map.insert("timeout".to_owned(), "soon".to_owned());
+    let err = CommandBuilder::from_str_map(&map).unwrap_err();
+    assert_eq!(err.field, "timeout");
+    assert_eq!(err.value, "soon");
+    assert_eq!(
+        err.to_string(),
+        "invalid value 'soon' for 'timeout': invalid digit found in string"
+    );
+
+    std::env::set_var("APP_EXECUTABLE", "rustc");
+    std::env::set_var("APP_TIMEOUT", "5");
+    std::env::set_var("TEST_COMMAND_CWD", "/tmp");
+    let command = CommandBuilder::from_env("APP_")
+        .unwrap()
+        .arg("--version".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "rustc");
+    assert_eq!(command.args, vec!["--version"]);
+    assert_eq!(command.current_dir, Some(PathBuf::from("/tmp")));
+    assert_eq!(command.timeout, 5);
+
+    // Fields missing from the environment are left unset for build() to
+    // report.
+    let err = CommandBuilder::from_env("MISSING_").unwrap().build().unwrap_err();
+    assert_eq!(err.to_string(), "'executable', 'timeout' fields have not been set");
+}
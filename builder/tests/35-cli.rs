@@ -0,0 +1,83 @@
+// With #[builder(cli)] on the struct, the builder can be created from command
+// line arguments by parse_args(). Each field is set by an option named after
+// it, as in `--current-dir <VALUE>` or `--current-dir=<VALUE>`, whose value
+// is parsed through FromStr; an `each` collection gets one item per use of
+// the option named after its `each` setter, and a bool field is a flag set by
+// its option alone, which is false when the option is not given.
+//
+// `--help` is answered with a description of the options built from the
+// fields' doc comments, also available from help().
+
+use derive_builder::Builder;
+use std::path::PathBuf;
+
+#[derive(Builder, Debug)]
+#[builder(cli, derive(Debug))]
+pub struct Command {
+    /// The program to run.
+    executable: String,
+    /// An argument to pass to the program.
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    /// The directory to run the program in.
+    current_dir: Option<PathBuf>,
+    /// Print each command before running it.
+    verbose: bool,
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn main() {
+    let command = CommandBuilder::parse_args(args(&[
+        "--executable",
+        "cargo",
+        "--arg",
+        "build",
+        "--arg=--release",
+        "--current-dir",
+        "/tmp",
+        "--verbose",
+    ]))
+    .unwrap()
+    .build()
+    .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir, Some(PathBuf::from("/tmp")));
+    assert!(command.verbose);
+
+    let command = CommandBuilder::parse_args(args(&["--executable=rustc"]))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(command.args.is_empty());
+    assert!(!command.verbose);
+
+    let err = CommandBuilder::parse_args(args(&["--verbose=maybe"])).unwrap_err();
+    assert_eq!(
+        err,
+        CommandBuilderArgsError::InvalidValue {
+            option: "--verbose",
+            value: "maybe".to_owned(),
+            message: "provided string was not `true` or `false`".to_owned(),
+        }
+    );
+    let err = CommandBuilder::parse_args(args(&["--executable"])).unwrap_err();
+    assert_eq!(err, CommandBuilderArgsError::MissingValue("--executable"));
+    let err = CommandBuilder::parse_args(args(&["--timeout", "5"])).unwrap_err();
+    assert_eq!(err.to_string(), "unknown argument '--timeout'");
+
+    let help = "\
+Options:
+  --executable <VALUE>   The program to run.
+  --arg <VALUE>...       An argument to pass to the program.
+  --current-dir <VALUE>  The directory to run the program in.
+  --verbose              Print each command before running it.
+  --help                 Print this help
+";
+    assert_eq!(CommandBuilder::help(), help);
+    let err = CommandBuilder::parse_args(args(&["--arg", "x", "--help"])).unwrap_err();
+    assert_eq!(err, CommandBuilderArgsError::Help(help.to_owned()));
+}
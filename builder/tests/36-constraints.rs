@@ -0,0 +1,67 @@
+// Some fields only make sense together, or not at all together. On a field,
+// #[builder(requires = "other")] makes build() fail if the field is set but
+// `other` is not, and #[builder(conflicts_with = "other")] makes it fail if
+// both are set. On the struct, #[builder(one_of("a", "b"))] makes build()
+// fail unless exactly one of the listed fields is set.
+//
+// Each of these failures is reported through the Constraint variant of the
+// builder's error enum, separately from missing required fields.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(one_of("password", "token"))]
+pub struct Login {
+    user: String,
+    #[builder(conflicts_with = "token")]
+    password: Option<String>,
+    token: Option<String>,
+    #[builder(requires = "key")]
+    cert: Option<String>,
+    key: Option<String>,
+}
+
+fn main() {
+    let login = Login::builder()
+        .user("ferris".to_owned())
+        .token("t0k3n".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(login.token.as_deref(), Some("t0k3n"));
+
+    let err = Login::builder()
+        .user("ferris".to_owned())
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "exactly one of 'password', 'token' must be set");
+
+    let err = Login::builder()
+        .user("ferris".to_owned())
+        .password("hunter2".to_owned())
+        .token("t0k3n".to_owned())
+        .build()
+        .unwrap_err();
+    match err {
+        LoginBuilderError::Constraint(ref msg) => {
+            assert_eq!(msg, "'password' conflicts with 'token'");
+        }
+        _ => unreachable!(),
+    }
+
+    let err = Login::builder()
+        .user("ferris".to_owned())
+        .password("hunter2".to_owned())
+        .cert("cert.pem".to_owned())
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "'cert' requires 'key'");
+
+    let login = Login::builder()
+        .user("ferris".to_owned())
+        .password("hunter2".to_owned())
+        .cert("cert.pem".to_owned())
+        .key("key.pem".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(login.key.as_deref(), Some("key.pem"));
+}
@@ -0,0 +1,24 @@
+// Besides a setter per field, the builder has methods generated from the
+// field names, such as `dir_opt()` and `clear_dir()` for an optional `dir`,
+// and methods of its own such as `merge()` and `missing_fields()`. When one
+// of those would have the same name as a field's setter, the derive reports
+// it on the field instead of generating a builder that fails
+// to compile with duplicate definitions. Giving the setter another name with
+// #[builder(setter(name = "..."))] resolves it.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    dir: Option<String>,
+    dir_opt: Option<String>,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    clear_args: bool,
+    #[builder(setter(name = "clear_dir2"))]
+    clear_dir: bool,
+    merge: bool,
+    missing_fields: Vec<String>,
+}
+
+fn main() {}
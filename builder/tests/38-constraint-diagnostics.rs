@@ -0,0 +1,17 @@
+// Constraints are checked when the builder is derived as far as they can be:
+// a `one_of` group must name at least two fields, as one alone would simply
+// be a required field and none would make build() always fail, and every
+// name given to a constraint must be a field which the builder can set.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(one_of(), one_of("token"), one_of("password", "passwd"))]
+pub struct Login {
+    password: Option<String>,
+    token: Option<String>,
+    #[builder(requires = "key")]
+    cert: Option<String>,
+}
+
+fn main() {}
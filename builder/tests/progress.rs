@@ -10,4 +10,33 @@ fn tests() {
     t.pass("tests/07-repeated-field.rs");
     t.compile_fail("tests/08-unrecognized-attribute.rs");
     t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/10-owned-pattern.rs");
+    t.pass("tests/11-typestate.rs");
+    t.compile_fail("tests/12-typestate-missing-field.rs");
+    t.pass("tests/13-error-enum.rs");
+    t.pass("tests/14-default-fields.rs");
+    t.pass("tests/15-setter-into.rs");
+    t.pass("tests/16-generics.rs");
+    t.pass("tests/17-validate.rs");
+    t.pass("tests/18-enum-variants.rs");
+    t.pass("tests/19-tuple-struct.rs");
+    t.pass("tests/20-naming.rs");
+    t.pass("tests/21-visibility-and-skip.rs");
+    t.compile_fail("tests/22-private-setter.rs");
+    t.pass("tests/23-each-collections.rs");
+    t.pass("tests/24-optional-required.rs");
+    t.compile_fail("tests/25-diagnostics.rs");
+    t.pass("tests/26-clear-and-opt-setters.rs");
+    t.pass("tests/27-sub-builder.rs");
+    t.pass("tests/28-to-builder.rs");
+    t.pass("tests/29-merge.rs");
+    t.pass("tests/30-builder-derives.rs");
+    t.pass("tests/31-missing-fields.rs");
+    t.pass("tests/32-try-setter.rs");
+    t.pass("tests/33-transform-and-wrap.rs");
+    t.pass("tests/34-from-env.rs");
+    t.pass("tests/35-cli.rs");
+    t.pass("tests/36-constraints.rs");
+    t.compile_fail("tests/37-method-name-clash.rs");
+    t.compile_fail("tests/38-constraint-diagnostics.rs");
 }